@@ -0,0 +1,123 @@
+//! Throughput of appending lines to a LogMonitor
+//!
+//! Run with `cargo bench`. Each case appends lines as fast as possible and
+//! reports lines per second, which should stay well above the 100k lines
+//! per second of a busy server, even when the content is full and every
+//! new line drops the oldest.
+#[path = "../src/custom/mod.rs"]
+pub mod custom;
+#[path = "../src/mod.rs"]
+pub mod shared;
+
+use std::time::{Duration, Instant, SystemTime};
+
+use custom::app::LogMonitor;
+use custom::opt::Collapse;
+use shared::util::RingList;
+
+/// Target throughput, in lines per second
+const TARGET_RATE: f64 = 100_000.0;
+
+/// Number of lines appended in each case
+const LINES: usize = 1_000_000;
+
+fn main() {
+	let lines = sample_lines(LINES);
+
+	for lines_max in [1_000, 100_000].iter() {
+		let mut monitor = LogMonitor::new("bench.log".to_string(), *lines_max);
+		monitor.collapse = Collapse::None;
+		let arrived = Some(SystemTime::now());
+		let elapsed = time(|| {
+			for line in lines.iter() {
+				monitor.append_line(line, arrived);
+			}
+		});
+		report(
+			&format!("LogMonitor::append_line, --lines-max {}", lines_max),
+			LINES,
+			elapsed,
+		);
+	}
+
+	// The content storage alone, already full, compared with trimming a Vec
+	let lines_max = 100_000;
+	let mut ring = RingList::new(lines_max);
+	lines.iter().take(lines_max).for_each(|line| {
+		ring.push(line.as_str());
+	});
+	let elapsed = time(|| {
+		for line in lines.iter() {
+			ring.push(line.as_str());
+		}
+	});
+	report("RingList::push when full", LINES, elapsed);
+
+	let mut vec: Vec<&str> = lines
+		.iter()
+		.take(lines_max)
+		.map(|line| line.as_str())
+		.collect();
+	let trimmed_lines = LINES / 100; // Far slower, so fewer
+	let elapsed = time(|| {
+		for line in lines.iter().take(trimmed_lines) {
+			vec.push(line.as_str());
+			if vec.len() > lines_max {
+				vec = vec.split_off(vec.len() - lines_max);
+			}
+		}
+	});
+	report(
+		"Vec::push with split_off when full (previous)",
+		trimmed_lines,
+		elapsed,
+	);
+}
+
+/// Varied lines in the style of a server log, a tenth with ISO timestamps
+fn sample_lines(count: usize) -> Vec<String> {
+	let messages = [
+		"INFO request {} completed in {}ms",
+		"DEBUG cache miss for key {} after {}ms",
+		"WARN retrying request {} (attempt {})",
+		"ERROR timeout waiting for 10.0.0.{} after {}ms",
+	];
+	(0..count)
+		.map(|n| {
+			let message = messages[n % messages.len()]
+				.replacen("{}", &(n * 7919 % 100_000).to_string(), 1)
+				.replacen("{}", &(n % 250).to_string(), 1);
+			match n % 10 {
+				0 => format!(
+					"2024-01-02T03:04:{:02}.{:03}Z {}",
+					n / 1000 % 60,
+					n % 1000,
+					message
+				),
+				_ => message,
+			}
+		})
+		.collect()
+}
+
+fn time(f: impl FnOnce()) -> Duration {
+	let started = Instant::now();
+	f();
+	started.elapsed()
+}
+
+fn report(case: &str, lines: usize, elapsed: Duration) {
+	let rate = lines as f64 / elapsed.as_secs_f64();
+	let verdict = match rate >= TARGET_RATE {
+		true => "ok",
+		false => "BELOW TARGET",
+	};
+	println!(
+		"{:<50} {:>12.0} lines/s  ({} lines in {:.2}s) {}",
+		case,
+		rate,
+		lines,
+		elapsed.as_secs_f64(),
+		verdict
+	);
+}
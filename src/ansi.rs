@@ -0,0 +1,223 @@
+//! ANSI escape sequences embedded in log lines
+//!
+//! SGR (Select Graphic Rendition) sequences are converted to tui styles
+//! so that coloured logs display as intended. All other escape and
+//! control sequences are removed, as they would disrupt the display.
+use std::borrow::Cow;
+
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+enum Piece<'a> {
+	Text(&'a str),
+	Sgr(&'a str), // Parameters of an SGR sequence
+}
+
+/// Split text into plain text and SGR parameters, dropping other control sequences
+fn pieces(text: &str) -> Vec<Piece<'_>> {
+	let mut pieces = Vec::new();
+	let mut chars = text.char_indices().peekable();
+	let mut text_start = 0;
+
+	while let Some((i, c)) = chars.next() {
+		if c != ESC && (c == '\t' || !c.is_control()) {
+			continue;
+		}
+		if text_start < i {
+			pieces.push(Piece::Text(&text[text_start..i]));
+		}
+		text_start = i + c.len_utf8();
+		if c != ESC {
+			continue; // Other control characters are dropped
+		}
+
+		match chars.peek().copied() {
+			// CSI: ESC [ parameters intermediates final
+			Some((_, '[')) => {
+				chars.next();
+				let params_start = i + 2;
+				let mut end = text.len();
+				let mut final_byte = None;
+				for (j, c) in chars.by_ref() {
+					if ('\x40'..='\x7e').contains(&c) {
+						end = j;
+						final_byte = Some(c);
+						break;
+					}
+				}
+				if final_byte == Some('m') {
+					pieces.push(Piece::Sgr(&text[params_start..end]));
+				}
+				text_start = match final_byte {
+					Some(c) => end + c.len_utf8(),
+					None => text.len(),
+				};
+			}
+			// OSC and similar strings: terminated by BEL or ESC \
+			Some((_, ']')) | Some((_, 'P')) | Some((_, '_')) | Some((_, '^')) => {
+				chars.next();
+				text_start = text.len();
+				while let Some((j, c)) = chars.next() {
+					if c == BEL {
+						text_start = j + 1;
+						break;
+					}
+					if c == ESC {
+						if let Some((k, '\\')) = chars.peek().copied() {
+							text_start = k + 1;
+							chars.next();
+							break;
+						}
+					}
+				}
+			}
+			// Other escape sequences: ESC intermediates final (e.g. ESC ( B)
+			Some(_) => {
+				text_start = text.len();
+				for (j, c) in chars.by_ref() {
+					if !(' '..='/').contains(&c) {
+						text_start = j + c.len_utf8();
+						break;
+					}
+				}
+			}
+			None => (),
+		}
+	}
+	if text_start < text.len() {
+		pieces.push(Piece::Text(&text[text_start..]));
+	}
+	pieces
+}
+
+/// Remove all escape sequences and control characters (other than tab)
+pub fn strip_ansi(text: &str) -> String {
+	let mut stripped = String::with_capacity(text.len());
+	for piece in pieces(text) {
+		if let Piece::Text(text) = piece {
+			stripped.push_str(text);
+		}
+	}
+	stripped
+}
+
+/// The text without escape sequences, borrowed if it has none to remove
+pub fn plain_text(text: &str) -> Cow<'_, str> {
+	match text.chars().any(|c| c != '\t' && c.is_control()) {
+		true => Cow::from(strip_ansi(text)),
+		false => Cow::from(text),
+	}
+}
+
+/// Convert text to spans styled according to any SGR sequences it contains
+///
+/// The base style applies where the text doesn't set its own.
+pub fn ansi_to_spans(text: &str, base: Style) -> Spans<'static> {
+	let mut spans = Vec::new();
+	let mut style = base;
+	for piece in pieces(text) {
+		match piece {
+			Piece::Text(text) => spans.push(Span::styled(text.to_string(), style)),
+			Piece::Sgr(params) => style = apply_sgr(style, base, params),
+		}
+	}
+	Spans::from(spans)
+}
+
+fn apply_sgr(mut style: Style, base: Style, params: &str) -> Style {
+	let mut codes = params
+		.split(&[';', ':'][..])
+		.map(|code| code.parse::<u16>().unwrap_or(0));
+
+	// An empty sequence (ESC [ m) is a reset
+	if params.is_empty() {
+		return base;
+	}
+
+	while let Some(code) = codes.next() {
+		style = match code {
+			0 => base,
+			1 => style.add_modifier(Modifier::BOLD),
+			2 => style.add_modifier(Modifier::DIM),
+			3 => style.add_modifier(Modifier::ITALIC),
+			4 => style.add_modifier(Modifier::UNDERLINED),
+			5 => style.add_modifier(Modifier::SLOW_BLINK),
+			6 => style.add_modifier(Modifier::RAPID_BLINK),
+			7 => style.add_modifier(Modifier::REVERSED),
+			8 => style.add_modifier(Modifier::HIDDEN),
+			9 => style.add_modifier(Modifier::CROSSED_OUT),
+			22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+			23 => style.remove_modifier(Modifier::ITALIC),
+			24 => style.remove_modifier(Modifier::UNDERLINED),
+			25 => style.remove_modifier(Modifier::SLOW_BLINK | Modifier::RAPID_BLINK),
+			27 => style.remove_modifier(Modifier::REVERSED),
+			28 => style.remove_modifier(Modifier::HIDDEN),
+			29 => style.remove_modifier(Modifier::CROSSED_OUT),
+			30..=37 => style.fg(basic_colour(code - 30)),
+			90..=97 => style.fg(bright_colour(code - 90)),
+			40..=47 => style.bg(basic_colour(code - 40)),
+			100..=107 => style.bg(bright_colour(code - 100)),
+			38 => match extended_colour(&mut codes) {
+				Some(colour) => style.fg(colour),
+				None => style,
+			},
+			48 => match extended_colour(&mut codes) {
+				Some(colour) => style.bg(colour),
+				None => style,
+			},
+			39 => Style {
+				fg: base.fg,
+				..style
+			},
+			49 => Style {
+				bg: base.bg,
+				..style
+			},
+			_ => style,
+		};
+	}
+	style
+}
+
+/// Parse the rest of a 38 or 48 sequence: 5;n (256 colours) or 2;r;g;b
+fn extended_colour(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+	match codes.next()? {
+		5 => Some(Color::Indexed(codes.next()? as u8)),
+		2 => {
+			let r = codes.next()? as u8;
+			let g = codes.next()? as u8;
+			let b = codes.next()? as u8;
+			Some(Color::Rgb(r, g, b))
+		}
+		_ => None,
+	}
+}
+
+fn basic_colour(n: u16) -> Color {
+	match n {
+		0 => Color::Black,
+		1 => Color::Red,
+		2 => Color::Green,
+		3 => Color::Yellow,
+		4 => Color::Blue,
+		5 => Color::Magenta,
+		6 => Color::Cyan,
+		_ => Color::Gray,
+	}
+}
+
+fn bright_colour(n: u16) -> Color {
+	match n {
+		0 => Color::DarkGray,
+		1 => Color::LightRed,
+		2 => Color::LightGreen,
+		3 => Color::LightYellow,
+		4 => Color::LightBlue,
+		5 => Color::LightMagenta,
+		6 => Color::LightCyan,
+		_ => Color::White,
+	}
+}
@@ -17,15 +17,21 @@ use tokio::sync::mpsc;
 ///! forks of logterm customise the files in src/custom
 #[path = "../custom/mod.rs"]
 pub mod custom;
-use self::custom::app::{App, DashViewMain};
+use self::custom::app::App;
+use self::custom::keymap::Action;
 use self::custom::ui::draw_dashboard;
 
 ///! logtail and its forks share code in src/
 #[path = "../mod.rs"]
 pub mod shared;
+use shared::syslog::SyslogReceiver;
+use shared::timer::Timer;
 
 use crossterm::{
-	event::{self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, KeyCode},
+	event::{
+		self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, KeyCode, KeyEvent,
+		KeyModifiers,
+	},
 	execute,
 	terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -34,7 +40,6 @@ use std::{
 	error::Error,
 	io::{stdout, Write},
 	thread,
-	time::{Duration, Instant},
 };
 
 use tui::{
@@ -54,7 +59,8 @@ use futures::{
 
 enum Event<I> {
 	Input(I),
-	Tick,
+	Resize,
+	Tick, // Woken by the timer
 }
 
 // RUSTFLAGS="-A unused" cargo run --bin logtail-crossterm --features="crossterm" /var/log/auth.log /var/log/dmesg
@@ -65,54 +71,64 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
 		Err(e) => return Ok(()),
 	};
 
+	let mut syslog = app.start_syslog_receiver()?;
+
 	// Terminal initialization
 	enable_raw_mode()?;
 	let mut stdout = stdout();
 	execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
 	let backend = CrosstermBackend::new(stdout);
 	let mut terminal = Terminal::new(backend)?;
-	let mut rx = initialise_events(app.opt.tick_rate);
+	let (mut rx, timer) = initialise_events();
 	terminal.clear()?;
 
 	// Use futures of async functions to handle events
-	// concurrently with logfile changes.
+	// concurrently with logfile changes. The dashboard is drawn when
+	// anything has changed, and the timer is only set for things which
+	// depend on the time.
 	loop {
-		terminal.draw(|f| draw_dashboard(f, &mut app.dash_state, &mut app.monitors))?;
+		app.update_followed_logfiles().await;
+		let size = terminal.size()?;
+		if app.needs_redraw(size) {
+			let render_started = std::time::Instant::now();
+			terminal.draw(|f| draw_dashboard(f, &mut app.dash_state, &mut app.monitors))?;
+			app.dash_state.record_render(render_started.elapsed());
+			app.mark_drawn(size);
+		}
+		if let Some(when) = app.next_wakeup() {
+			timer.wake_at(when);
+		}
+
 		let logfiles_future = app.logfiles.next().fuse();
 		let events_future = rx.recv().fuse();
-		pin_mut!(logfiles_future, events_future);
+		let syslog_future = SyslogReceiver::next(&mut syslog).fuse();
+		pin_mut!(logfiles_future, events_future, syslog_future);
 
 		select! {
 			(e) = events_future => {
 				match e {
-					Some(Event::Input(event)) => match event.code {
-					KeyCode::Char('q')|
-					KeyCode::Char('Q') => {
-						disable_raw_mode()?;
-						execute!(
-							terminal.backend_mut(),
-							LeaveAlternateScreen,
-							DisableMouseCapture
-						)?;
-						terminal.show_cursor()?;
-						break Ok(());
-					},
-					KeyCode::Char('h')|
-					KeyCode::Char('H') => app.dash_state.main_view = DashViewMain::DashHorizontal,
-					KeyCode::Char('v')|
-					KeyCode::Char('V') => app.dash_state.main_view = DashViewMain::DashVertical,
-					KeyCode::Down => app.handle_arrow_down(),
-					KeyCode::Up => app.handle_arrow_up(),
-					KeyCode::Right|
-					KeyCode::Tab => app.change_focus_next(),
-					KeyCode::Left => app.change_focus_previous(),
-					_ => {},
+					Some(Event::Input(event)) => {
+						if let Some(key) = key_name(event) {
+							match app.handle_key(&key) {
+								Some(Action::Quit) => {
+									disable_raw_mode()?;
+									execute!(
+										terminal.backend_mut(),
+										LeaveAlternateScreen,
+										DisableMouseCapture
+									)?;
+									terminal.show_cursor()?;
+									break Ok(app.save_session()?);
+								},
+								Some(action) => app.do_action(action),
+								None => {},
+							}
+						}
 					}
 
-					Some(Event::Tick) => {
-					// draw_dashboard(&mut f, &dash_state, &mut monitors).unwrap();
-					// draw_dashboard(f, &dash_state, &mut monitors)?;
-					}
+					Some(Event::Resize) => (), // Drawn at the new size straight away
+
+					Some(Event::Tick) => app.on_tick(),
 
 					None => (),
 				}
@@ -121,48 +137,74 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
 			(line) = logfiles_future => {
 				match line {
 					Some(Ok(line)) => {
-					let source_str = line.source().to_str().unwrap();
-					let source = String::from(source_str);
-						match app.get_monitor_for_file_path(&source) {
-							Some(monitor) => monitor.append_to_content(line.line()),
-							None => panic!("No monitor for file: {}",&source),
-						}
+						let source = line.source().to_string_lossy().to_string();
+						app.read_logfile_lines(&source);
+					},
+					Some(Err(e)) => {
+						app.dash_state.diagnostic(&format!("watcher error: {}", e));
+						app.read_all_logfile_lines();
 					},
-					Some(Err(e)) => panic!("{}", e),
 					None => (),
 				}
 			},
+			(message) = syslog_future => app.append_syslog_message(&message),
 		}
 	}
 }
 // type Tx = std::sync::mpsc::Sender<Event<crossterm::event::KeyEvent>>;
 type Rx = tokio::sync::mpsc::UnboundedReceiver<Event<crossterm::event::KeyEvent>>;
 
-fn initialise_events(tick_rate: u64) -> Rx {
-	let tick_rate = Duration::from_millis(tick_rate);
+fn initialise_events() -> (Rx, Timer) {
 	let (tx, rx) = mpsc::unbounded_channel(); // Setup input handling
-
-	thread::spawn(move || {
-		let mut last_tick = Instant::now();
-		loop {
-			// poll for tick rate duration, if no events, sent tick event.
-			if event::poll(tick_rate - last_tick.elapsed()).unwrap() {
-				if let CEvent::Key(key) = event::read().unwrap() {
-					tx.send(Event::Input(key));
-				}
-			}
-			if last_tick.elapsed() >= tick_rate {
-				tx.send(Event::Tick);
-				last_tick = Instant::now();
+	let timer = Timer::new(tx.clone(), || Event::Tick);
+
+	thread::spawn(move || loop {
+		let event = match event::read() {
+			Ok(CEvent::Key(key)) => Event::Input(key),
+			Ok(CEvent::Resize(_, _)) => Event::Resize,
+			Ok(_) => continue,
+			Err(e) => {
+				println!("input error: {}", e);
+				return;
 			}
+		};
+		if tx.send(event).is_err() {
+			return;
+		}
+	});
+	(rx, timer)
+}
 
-			if last_tick.elapsed() >= tick_rate {
-				match tx.send(Event::Tick) {
-					Ok(()) => last_tick = Instant::now(),
-					Err(e) => println!("send error: {}", e),
-				}
+/// Name a crossterm key in the form used by the keymap
+fn key_name(event: KeyEvent) -> Option<String> {
+	let name = match event.code {
+		KeyCode::Char(' ') => String::from("Space"),
+		KeyCode::Char(c) => {
+			if event.modifiers.contains(KeyModifiers::CONTROL) {
+				format!("C-{}", c)
+			} else if event.modifiers.contains(KeyModifiers::ALT) {
+				format!("M-{}", c)
+			} else {
+				c.to_string()
 			}
 		}
-	});
-	rx
+		KeyCode::F(n) => format!("F{}", n),
+		KeyCode::Tab => String::from("Tab"),
+		KeyCode::Enter => String::from("Enter"),
+		KeyCode::Up => String::from("Up"),
+		KeyCode::Down => String::from("Down"),
+		KeyCode::Left => String::from("Left"),
+		KeyCode::Right => String::from("Right"),
+		KeyCode::BackTab => String::from("BackTab"),
+		KeyCode::Esc => String::from("Esc"),
+		KeyCode::Backspace => String::from("Backspace"),
+		KeyCode::Delete => String::from("Delete"),
+		KeyCode::Insert => String::from("Insert"),
+		KeyCode::Home => String::from("Home"),
+		KeyCode::End => String::from("End"),
+		KeyCode::PageUp => String::from("PageUp"),
+		KeyCode::PageDown => String::from("PageDown"),
+		_ => return None,
+	};
+	Some(name)
 }
@@ -0,0 +1,445 @@
+//! Writes synthetic logs for load and UI testing of logtail and its forks
+//!
+//! Lines are written to one or more logfiles at a rate which varies over
+//! time, with a mix of levels, occasional bursts, multi-line stack traces
+//! for some errors, and rotation of the logfiles. Output is plain text or
+//! JSON (one object per line).
+//!
+//! For example, to generate around 50 lines a second and watch them:
+//!
+//!   logtail-gen --rate 50 /tmp/gen/a.log /tmp/gen/b.log
+//!   logtail /tmp/gen/a.log /tmp/gen/b.log
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use chrono::{SecondsFormat, Utc};
+use structopt::StructOpt;
+
+// logtail and its forks share code in src/
+#[path = "../mod.rs"]
+pub mod shared;
+use shared::util::{RandomSignal, SinSignal};
+
+/// How the rate of lines varies over time
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Signal {
+	Constant,
+	Random,
+	Sine,
+}
+
+impl std::str::FromStr for Signal {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"constant" => Ok(Signal::Constant),
+			"random" => Ok(Signal::Random),
+			"sine" => Ok(Signal::Sine),
+			_ => Err(format!("unknown signal: {}", s)),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+	Plain,
+	Json,
+}
+
+impl std::str::FromStr for Format {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"plain" => Ok(Format::Plain),
+			"json" => Ok(Format::Json),
+			_ => Err(format!("unknown format: {}", s)),
+		}
+	}
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Write synthetic logs for testing logtail.")]
+struct GenOpt {
+	/// Average number of lines per second, across all logfiles
+	#[structopt(short, long, default_value = "10")]
+	rate: f64,
+
+	/// How the rate varies: 'constant', 'random' or 'sine'
+	#[structopt(long, default_value = "sine", possible_values = &["constant", "random", "sine"])]
+	signal: Signal,
+
+	/// Period of the sine signal in seconds
+	#[structopt(long, default_value = "60")]
+	period: f64,
+
+	/// Chance each second of a burst of lines
+	#[structopt(long, default_value = "0.02")]
+	burst_chance: f64,
+
+	/// Number of lines in a burst
+	#[structopt(long, default_value = "200")]
+	burst_lines: usize,
+
+	/// Chance that an error line is followed by a stack trace
+	#[structopt(long, default_value = "0.3")]
+	trace_chance: f64,
+
+	/// Line format: 'plain' or 'json'
+	#[structopt(long, default_value = "plain", possible_values = &["plain", "json"])]
+	format: Format,
+
+	/// Rotate a logfile after this many lines (0 for never)
+	#[structopt(long, default_value = "0", value_name = "LINES")]
+	rotate_lines: usize,
+
+	/// Number of rotated logfiles to keep (LOGFILE.1, LOGFILE.2...)
+	#[structopt(long, default_value = "3")]
+	rotate_keep: usize,
+
+	/// Stop after this many seconds (0 to run until interrupted)
+	#[structopt(short, long, default_value = "0")]
+	duration: u64,
+
+	/// Logfiles to write (appended to if they exist)
+	#[structopt(name = "LOGFILE", required = true)]
+	files: Vec<String>,
+}
+
+/// Time between writes
+const TICK: Duration = Duration::from_millis(100);
+
+static LEVELS: [(&str, u64); 4] = [("DEBUG", 20), ("INFO", 60), ("WARN", 12), ("ERROR", 8)];
+
+static COMPONENTS: [&str; 6] = ["http", "db", "auth", "cache", "scheduler", "storage"];
+
+static MESSAGES: [&str; 12] = [
+	"request {id} completed in {ms}ms",
+	"user {user} logged in from {ip}",
+	"user {user} logged out",
+	"connection from {ip} closed after {ms}ms",
+	"cache miss for key {hex}",
+	"query took {ms}ms rows={n}",
+	"job {id} scheduled for {n}s from now",
+	"disk usage at {pct}%",
+	"timeout waiting for {ip} after {ms}ms",
+	"retrying request {id} (attempt {n})",
+	"failed to write block {hex}: no space left on device",
+	"session {hex} expired",
+];
+
+static TRACE_FRAMES: [&str; 6] = [
+	"storage::block::write",
+	"storage::volume::flush",
+	"db::pool::Connection::execute",
+	"http::server::handle_request",
+	"scheduler::worker::run",
+	"core::ops::function::FnOnce::call_once",
+];
+
+/// Random numbers for choices and probabilities
+struct Dice {
+	signal: RandomSignal,
+}
+
+impl Dice {
+	const SIDES: u64 = 1_000_000; // Resolution of probabilities
+
+	fn new() -> Dice {
+		Dice {
+			signal: RandomSignal::new(0, u64::MAX),
+		}
+	}
+
+	/// A number from 0 up to (but not including) n
+	fn roll(&mut self, n: u64) -> u64 {
+		self.signal.next().unwrap_or(0) % n.max(1)
+	}
+
+	/// True with the given probability
+	fn chance(&mut self, probability: f64) -> bool {
+		(self.roll(Dice::SIDES) as f64) < probability * Dice::SIDES as f64
+	}
+
+	fn choose<'a>(&mut self, items: &[&'a str]) -> &'a str {
+		items[self.roll(items.len() as u64) as usize]
+	}
+
+	fn level(&mut self) -> &'static str {
+		let total: u64 = LEVELS.iter().map(|(_, weight)| weight).sum();
+		let mut roll = self.roll(total);
+		for (level, weight) in LEVELS.iter() {
+			if roll < *weight {
+				return level;
+			}
+			roll -= weight;
+		}
+		LEVELS[0].0
+	}
+}
+
+/// A logfile being written, and the number of lines since it was rotated
+struct Output {
+	path: String,
+	file: File,
+	lines: usize,
+}
+
+impl Output {
+	fn open(path: &str) -> std::io::Result<Output> {
+		Ok(Output {
+			path: path.to_string(),
+			file: OpenOptions::new().create(true).append(true).open(path)?,
+			lines: 0,
+		})
+	}
+
+	/// Rename the logfile to LOGFILE.1 (and older ones to .2, .3...) and start a new one
+	fn rotate(&mut self, keep: usize) -> std::io::Result<()> {
+		for n in (1..keep).rev() {
+			let older = format!("{}.{}", self.path, n);
+			if Path::new(&older).exists() {
+				std::fs::rename(&older, format!("{}.{}", self.path, n + 1))?;
+			}
+		}
+		if keep > 0 {
+			std::fs::rename(&self.path, format!("{}.1", self.path))?;
+		} else {
+			std::fs::remove_file(&self.path)?;
+		}
+		*self = Output::open(&self.path)?;
+		Ok(())
+	}
+}
+
+fn main() -> std::io::Result<()> {
+	let opt = GenOpt::from_args();
+
+	let mut outputs = Vec::new();
+	for path in opt.files.iter() {
+		outputs.push(Output::open(path)?);
+	}
+
+	let mut dice = Dice::new();
+	let mut random_rate = RandomSignal::new(0, 200); // Percent of the average rate
+	let sine_period = opt.period / (2.0 * std::f64::consts::PI);
+	let mut sine_rate = SinSignal::new(TICK.as_secs_f64(), sine_period, 0.8);
+	let mut owed = 0.0; // Lines due but not yet written
+	let mut burst = 0;
+	let started = Instant::now();
+
+	loop {
+		let tick_started = Instant::now();
+		if opt.duration > 0 && started.elapsed() >= Duration::from_secs(opt.duration) {
+			return Ok(());
+		}
+
+		let factor = match opt.signal {
+			Signal::Constant => 1.0,
+			Signal::Random => random_rate.next().unwrap_or(100) as f64 / 100.0,
+			Signal::Sine => 1.0 + sine_rate.next().map_or(0.0, |(_, y)| y),
+		};
+		owed += opt.rate * factor * TICK.as_secs_f64();
+		if burst == 0 && dice.chance(opt.burst_chance * TICK.as_secs_f64()) {
+			burst = opt.burst_lines;
+		}
+
+		let burst_now = burst.min(opt.burst_lines / 10 + 1);
+		burst -= burst_now;
+		let lines = owed as usize + burst_now;
+		owed -= (owed as usize) as f64;
+
+		for _ in 0..lines {
+			let n = dice.roll(outputs.len() as u64) as usize;
+			let output = &mut outputs[n];
+			let text = log_entry(&mut dice, &opt);
+			output.file.write_all(text.as_bytes())?;
+			output.lines += 1;
+			if opt.rotate_lines > 0 && output.lines >= opt.rotate_lines {
+				output.rotate(opt.rotate_keep)?;
+			}
+		}
+		for output in outputs.iter_mut() {
+			output.file.flush()?;
+		}
+
+		if let Some(remaining) = TICK.checked_sub(tick_started.elapsed()) {
+			std::thread::sleep(remaining);
+		}
+	}
+}
+
+/// One log entry, which may be several lines if it includes a stack trace
+fn log_entry(dice: &mut Dice, opt: &GenOpt) -> String {
+	let time = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+	let level = dice.level();
+	let component = dice.choose(&COMPONENTS);
+	let message = dice_message(dice, level);
+	let message = fill_message(dice, message);
+
+	let trace: Vec<String> = match level == "ERROR" && dice.chance(opt.trace_chance) {
+		true => (0..2 + dice.roll(5))
+			.map(|_| {
+				format!(
+					"    at {} ({}.rs:{})",
+					dice.choose(&TRACE_FRAMES),
+					component,
+					1 + dice.roll(900)
+				)
+			})
+			.collect(),
+		false => Vec::new(),
+	};
+
+	match opt.format {
+		Format::Plain => {
+			let mut entry = format!("{} {:5} [{}] {}\n", time, level, component, message);
+			for frame in trace {
+				entry.push_str(&frame);
+				entry.push('\n');
+			}
+			entry
+		}
+		Format::Json => {
+			let mut entry = format!(
+				"{{\"time\":\"{}\",\"level\":\"{}\",\"component\":\"{}\",\"msg\":\"{}\"",
+				time,
+				level,
+				component,
+				json_escape(&message)
+			);
+			if !trace.is_empty() {
+				entry.push_str(&format!(",\"trace\":\"{}\"", json_escape(&trace.join("\n"))));
+			}
+			entry.push_str("}\n");
+			entry
+		}
+	}
+}
+
+/// A message suited to the level (errors and warnings tend to be about failures)
+fn dice_message(dice: &mut Dice, level: &str) -> &'static str {
+	match level {
+		"ERROR" => dice.choose(&[MESSAGES[8], MESSAGES[10]]),
+		"WARN" => dice.choose(&[MESSAGES[7], MESSAGES[8], MESSAGES[9]]),
+		_ => dice.choose(&MESSAGES[..8]),
+	}
+}
+
+/// Replace the placeholders in a message with random values
+fn fill_message(dice: &mut Dice, message: &str) -> String {
+	let mut text = message.to_string();
+	while let Some(start) = text.find('{') {
+		let end = match text[start..].find('}') {
+			Some(end) => start + end,
+			None => break,
+		};
+		let value = match &text[start + 1..end] {
+			"id" => format!("{}", 10000 + dice.roll(90000)),
+			"ms" => format!("{}", 1 + dice.roll(2000)),
+			"user" => format!("user{}", 1 + dice.roll(50)),
+			"ip" => format!("10.0.{}.{}", dice.roll(4), 1 + dice.roll(254)),
+			"hex" => format!("{:08x}", dice.roll(u32::MAX as u64)),
+			"n" => format!("{}", 1 + dice.roll(20)),
+			"pct" => format!("{}", 80 + dice.roll(20)),
+			_ => String::new(),
+		};
+		text.replace_range(start..=end, &value);
+	}
+	text
+}
+
+fn json_escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\t' => escaped.push_str("\\t"),
+			c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Dice, Format, GenOpt, Output, StructOpt, LEVELS};
+	use crate::shared::timestamp::parse_line_timestamp;
+
+	fn opt(format: &str) -> GenOpt {
+		GenOpt::from_iter(&[
+			"logtail-gen",
+			"--trace-chance",
+			"1",
+			"--format",
+			format,
+			"a.log",
+		])
+	}
+
+	#[test]
+	fn plain_entries_have_timestamp_level_and_trace() {
+		let (opt, mut dice) = (opt("plain"), Dice::new());
+		assert_eq!(opt.format, Format::Plain);
+		let mut traces = 0;
+		for _ in 0..500 {
+			let entry = super::log_entry(&mut dice, &opt);
+			let mut lines = entry.lines();
+			let first = lines.next().unwrap();
+			let words: Vec<&str> = first.split_whitespace().collect();
+			assert!(parse_line_timestamp(first).is_some(), "{}", first);
+			assert!(LEVELS.iter().any(|(level, _)| *level == words[1]));
+			assert!(words[2].starts_with('[') && words[2].ends_with(']'));
+			assert!(!first.contains('{'), "placeholder left in {}", first);
+
+			// Only errors have stack traces, which are indented
+			let trace: Vec<&str> = lines.collect();
+			assert!(trace.is_empty() || words[1] == "ERROR");
+			assert!(trace.iter().all(|line| line.starts_with("    at ")));
+			traces += (!trace.is_empty()) as usize;
+		}
+		assert!(traces > 0);
+	}
+
+	#[test]
+	fn json_entries_are_one_line_each() {
+		let (opt, mut dice) = (opt("json"), Dice::new());
+		for _ in 0..500 {
+			let entry = super::log_entry(&mut dice, &opt);
+			assert!(entry.starts_with("{\"time\":\"") && entry.ends_with("}\n"));
+			assert_eq!(entry.matches('\n').count(), 1);
+			assert!(entry.contains("\"level\":\"") && entry.contains("\"msg\":\""));
+		}
+		assert_eq!(
+			super::json_escape("a \"b\"\n\tc\\"),
+			"a \\\"b\\\"\\n\\tc\\\\"
+		);
+	}
+
+	#[test]
+	fn rotation_keeps_older_logfiles() {
+		let dir = std::env::temp_dir().join(format!("logtail-gen-rotate-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("a.log").to_str().unwrap().to_string();
+
+		let mut output = Output::open(&path).unwrap();
+		for n in 1..=3 {
+			std::fs::write(&path, format!("{}\n", n)).unwrap();
+			output.rotate(2).unwrap();
+		}
+		let read = |suffix: &str| std::fs::read_to_string(format!("{}{}", path, suffix)).ok();
+		assert_eq!(read(""), Some(String::new()));
+		assert_eq!(read(".1"), Some(String::from("3\n")));
+		assert_eq!(read(".2"), Some(String::from("2\n")));
+		assert_eq!(read(".3"), None);
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -20,7 +20,8 @@ use tokio_stream::StreamExt;
 ///! forks of logterm customise the files in src/custom
 #[path = "../custom/mod.rs"]
 pub mod custom;
-use self::custom::app::{App, DashState, DashViewMain, LogMonitor};
+use self::custom::app::{App, DashState, LogMonitor};
+use self::custom::keymap::Action;
 use self::custom::opt::Opt;
 use self::custom::ui::draw_dashboard;
 
@@ -29,6 +30,7 @@ use self::custom::ui::draw_dashboard;
 pub mod shared;
 use crate::shared::util::StatefulList;
 use shared::event::{Event, Events};
+use shared::syslog::SyslogReceiver;
 
 use termion::{event::Key, input::MouseTerminal, raw::IntoRawMode, screen::AlternateScreen};
 use tui::{
@@ -74,7 +76,9 @@ async fn terminal_main() -> std::io::Result<()> {
 		}
 	};
 
+	let mut syslog = app.start_syslog_receiver()?;
 	let mut events = Events::new();
+	events.disable_exit_key(); // Quit is handled by the keymap
 
 	// Terminal initialization
 	// info!("Intialising terminal (termion backend)");
@@ -85,41 +89,44 @@ async fn terminal_main() -> std::io::Result<()> {
 	let mut terminal = Terminal::new(backend)?;
 
 	// Use futures of async functions to handle events
-	// concurrently with logfile changes.
+	// concurrently with logfile changes. The dashboard is drawn when
+	// anything has changed, and the timer is only set for things which
+	// depend on the time.
 	// info!("Processing started");
 	loop {
+		app.update_followed_logfiles().await;
+		let size = terminal.size()?;
+		if app.needs_redraw(size) {
+			let render_started = std::time::Instant::now();
+			terminal.draw(|f| draw_dashboard(f, &mut app.dash_state, &mut app.monitors))?;
+			app.dash_state.record_render(render_started.elapsed());
+			app.mark_drawn(size);
+		}
+		if let Some(when) = app.next_wakeup() {
+			events.wake_at(when);
+		}
+
 		let events_future = events.rx.recv().fuse();
 		let logfiles_future = app.logfiles.next().fuse();
-		pin_mut!(events_future, logfiles_future);
+		let syslog_future = SyslogReceiver::next(&mut syslog).fuse();
+		pin_mut!(events_future, logfiles_future, syslog_future);
 
 		select! {
 			(e) = events_future => {
 				match e {
 					Some(Event::Input(input)) => {
-						match input {
-							Key::Char('q')|
-							Key::Char('Q') => return Ok(()),
-							Key::Char('h')|
-							Key::Char('H') => app.dash_state.main_view = DashViewMain::DashHorizontal,
-							Key::Char('v')|
-							Key::Char('V') => app.dash_state.main_view = DashViewMain::DashVertical,
-							Key::Down => app.handle_arrow_down(),
-							Key::Up => app.handle_arrow_up(),
-							Key::Right|
-							Key::Char('\t') => app.change_focus_next(),
-							Key::Left => app.change_focus_previous(),
-								_ => {},
+						if let Some(key) = key_name(input) {
+							match app.handle_key(&key) {
+								Some(Action::Quit) => return app.save_session(),
+								Some(action) => app.do_action(action),
+								None => {},
+							}
 						}
 					}
 
-					Some(Event::Tick) => {
-						match terminal.draw(|f| draw_dashboard(f, &mut app.dash_state, &mut app.monitors)) {
-							Ok(_) => {},
-							Err(e) => {
-								return Err(e);
-							}
-						};
-					}
+					Some(Event::Resize) => (), // Drawn at the new size straight away
+
+					Some(Event::Tick) => app.on_tick(),
 
 					None => return Err(Error::new(ErrorKind::Other, "receive error")),
 					// Err(e) => {
@@ -130,21 +137,45 @@ async fn terminal_main() -> std::io::Result<()> {
 			(line) = logfiles_future => {
 				match line {
 					Some(Ok(line)) => {
-						app.dash_state._debug_window(format!("logfile: {}", line.line()).as_str());
-						let source_str = line.source().to_str().unwrap();
-						let source = String::from(source_str);
-
-							match app.get_monitor_for_file_path(&source) {
-							Some(monitor) => monitor.append_to_content(line.line()),
-							None => panic!("No monitor for file: {}",&source),
-						}
+						let source = line.source().to_string_lossy().to_string();
+						app.read_logfile_lines(&source);
 					},
 					Some(Err(e)) => {
-						return Err(e)
+						app.dash_state.diagnostic(&format!("watcher error: {}", e));
+						app.read_all_logfile_lines();
 					},
 					None => (),
 				}
 			},
+			(message) = syslog_future => app.append_syslog_message(&message),
 		}
 	}
 }
+
+/// Name a termion key in the form used by the keymap
+fn key_name(key: Key) -> Option<String> {
+	let name = match key {
+		Key::Char('\t') => String::from("Tab"),
+		Key::Char('\n') => String::from("Enter"),
+		Key::Char(' ') => String::from("Space"),
+		Key::Char(c) => c.to_string(),
+		Key::Ctrl(c) => format!("C-{}", c),
+		Key::Alt(c) => format!("M-{}", c),
+		Key::F(n) => format!("F{}", n),
+		Key::Up => String::from("Up"),
+		Key::Down => String::from("Down"),
+		Key::Left => String::from("Left"),
+		Key::Right => String::from("Right"),
+		Key::BackTab => String::from("BackTab"),
+		Key::Esc => String::from("Esc"),
+		Key::Backspace => String::from("Backspace"),
+		Key::Delete => String::from("Delete"),
+		Key::Insert => String::from("Insert"),
+		Key::Home => String::from("Home"),
+		Key::End => String::from("End"),
+		Key::PageUp => String::from("PageUp"),
+		Key::PageDown => String::from("PageDown"),
+		_ => return None,
+	};
+	Some(name)
+}
@@ -4,14 +4,50 @@
 use std::fs::File;
 use std::io::{Error, ErrorKind, Write};
 
+use flate2::read::MultiGzDecoder;
 use linemux::MuxedLines;
-use std::collections::HashMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use structopt::StructOpt;
 
-use crate::custom::opt::Opt;
-use crate::shared::util::StatefulList;
+use crate::custom::command::{Command, CommandLine};
+use crate::custom::config::Config;
+use crate::custom::highlight::Highlighter;
+use crate::custom::keymap::{Action, Keymap};
+use crate::custom::opt::{Collapse, Gutter, Opt, SyslogPanes};
+use crate::custom::picker::{FilePicker, PickerOutcome};
+use crate::custom::replay::{Replay, ReplayLine, REPLAY_SEEK_STEP};
+use crate::custom::session::Session;
+use crate::custom::theme::{no_colour_requested, Theme, DEFAULT_THEME};
+use crate::shared::ansi::plain_text;
+use crate::shared::drain::TemplateMiner;
+use crate::shared::encoding::{
+	decode, decode_lines, hexdump_summary, is_binary, Encoding, BINARY_SAMPLE,
+};
+use crate::shared::source::{absolute_path, SourceIndex};
+use crate::shared::syslog::{SyslogMessage, SyslogReceiver};
+use crate::shared::tail::{file_id, read_head, read_last_lines, read_line, Follow, Scan};
+use crate::shared::timestamp::parse_line_timestamp;
+use crate::shared::util::{RingList, StatefulList};
+use tui::layout::Rect;
+use tui::widgets::ListState;
 
 pub static DEBUG_WINDOW_NAME: &str = "Debug Window";
+pub static SYSLOG_MONITOR_NAME: &str = "syslog";
+
+/// Time spent scanning logfile history on each tick (see --full-scan)
+pub const SCAN_TIME_PER_TICK: Duration = Duration::from_millis(50);
+
+/// How often the dashboard is drawn when nothing has changed but time
+/// has passed, to move the activity strips along and update the ages shown
+pub const IDLE_REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Renders taking at least this long are noted in the debug window
+pub const SLOW_RENDER: Duration = Duration::from_millis(50);
 
 pub struct App {
 	pub opt: Opt,
@@ -20,13 +56,99 @@ pub struct App {
 	pub logfile_with_focus: String,
 	pub logfiles: MuxedLines,
 	pub logfile_names: Vec<String>,
+	pub sources: SourceIndex,
+	pub keymap: Keymap,
+	pub replay: Option<Replay>,
+	pub opened: Vec<String>, // Logfiles opened since the last update_followed_logfiles()
+	pub closed: SourceIndex, // Logfiles closed, whose lines are dropped
+	pub closed_syslog_panes: Vec<String>, // Syslog panes closed, whose messages are dropped
 }
 
 impl App {
+	/// Set up from the command line and configuration file, and start following the logfiles
 	pub async fn new() -> Result<App, std::io::Error> {
-		let mut opt = Opt::from_args();
+		let opt = Opt::from_args();
+
+		let config = match Config::load(opt.config.as_deref()) {
+			Ok(config) => config,
+			Err(e) => {
+				println!("ERROR: unable to load configuration: {}", e);
+				return Err(e);
+			}
+		};
+
+		if opt.show_keys {
+			match Keymap::from_config(&config) {
+				Ok(keymap) => {
+					for line in keymap.describe() {
+						println!("{}", line);
+					}
+					std::process::exit(0);
+				}
+				Err(e) => {
+					println!("ERROR: in configuration: {}", e);
+					return Err(Error::new(ErrorKind::InvalidData, e));
+				}
+			}
+		}
+
+		let mut app = App::with_config(opt, config)?;
+		if let Some(name) = app.opt.session.clone() {
+			match Session::load(&name) {
+				Ok(session) => session.apply(&mut app),
+				Err(e) => {
+					println!("ERROR: in session: {}", e);
+					return Err(e);
+				}
+			}
+		}
+		app.follow_logfiles().await?;
+		Ok(app)
+	}
+
+	/// Save the dashboard state, if a session was given on the command line
+	pub fn save_session(&self) -> Result<(), std::io::Error> {
+		match &self.opt.session {
+			Some(name) => Session::from_app(self).save(name),
+			None => Ok(()),
+		}
+	}
+
+	/// Set up with the given options and configuration, loading existing
+	/// logfile content but not yet following the logfiles for new lines
+	pub fn with_config(opt: Opt, config: Config) -> Result<App, std::io::Error> {
+		let keymap = match Keymap::from_config(&config) {
+			Ok(keymap) => keymap,
+			Err(e) => {
+				println!("ERROR: in configuration: {}", e);
+				return Err(Error::new(ErrorKind::InvalidData, e));
+			}
+		};
+		let highlighter = match Highlighter::from_config(&config) {
+			Ok(highlighter) => highlighter,
+			Err(e) => {
+				println!("ERROR: in configuration: {}", e);
+				return Err(Error::new(ErrorKind::InvalidData, e));
+			}
+		};
+		let theme_name = opt
+			.theme
+			.clone()
+			.or_else(|| config.theme.clone())
+			.unwrap_or(DEFAULT_THEME.to_string());
+		let mut theme = match Theme::from_config(&theme_name, &config.themes) {
+			Ok(theme) => theme,
+			Err(e) => {
+				println!("ERROR: {}", e);
+				return Err(Error::new(ErrorKind::InvalidInput, e));
+			}
+		};
+		if no_colour_requested() {
+			theme = theme.without_colour();
+		}
 
-		if opt.files.is_empty() {
+		let syslog_enabled = opt.syslog_udp.is_some() || opt.syslog_tcp.is_some();
+		if opt.files.is_empty() && !syslog_enabled {
 			println!("{}: no logfile(s) specified.", Opt::clap().get_name());
 			println!(
 				"Try '{} --help' for more information.",
@@ -36,21 +158,47 @@ impl App {
 		}
 
 		let mut dash_state = DashState::new();
+		dash_state.theme = theme;
+		dash_state.strip_ansi = opt.strip_ansi || no_colour_requested();
+		dash_state.time_window = TimeWindow::from_opt(&opt);
+		dash_state.gutter = opt.gutter;
+		dash_state.highlighter = highlighter;
+		dash_state.debug_window = opt.debug_window;
 		let mut monitors: HashMap<String, LogMonitor> = HashMap::new();
-		let mut logfiles = MuxedLines::new()?;
+		let logfiles = MuxedLines::new()?;
 		let mut name_for_focus = String::new();
 		let mut logfile_names = Vec::<String>::new();
+		let mut sources = SourceIndex::new();
 
 		println!("Loading {} files...", opt.files.len());
 		for f in &opt.files {
 			println!("file: {}", f);
-			let mut monitor = LogMonitor::new(f.to_string(), opt.lines_max);
-			if opt.ignore_existing {
+			if let Some(same) = sources.add(f) {
+				dash_state.diagnostic(&format!("{} is the same file as {}", f, same));
+			}
+			let mut monitor = new_monitor(&opt, f);
+			if opt.ignore_existing || opt.replay {
+				if !opt.replay {
+					monitor.skip_existing()?;
+				}
 				logfile_names.push(f.to_string());
 				monitors.insert(f.to_string(), monitor);
 			} else {
-				match monitor.load_logfile() {
+				let loaded = if opt.rotated {
+					let max_age = match opt.rotated_max_age {
+						0 => None,
+						hours => Some(Duration::from_secs(hours * 60 * 60)),
+					};
+					monitor
+						.load_logfile()
+						.and_then(|()| monitor.load_rotated_logfiles(max_age))
+				} else {
+					monitor.load_logfile()
+				};
+				match loaded {
 					Ok(()) => {
+						dash_state
+							.diagnostic(&format!("loaded {} lines from {}", monitor.lines_seen, f));
 						logfile_names.push(f.to_string());
 						monitors.insert(f.to_string(), monitor);
 					}
@@ -64,18 +212,27 @@ impl App {
 			if name_for_focus.is_empty() {
 				name_for_focus = f.to_string();
 			}
+		}
 
-			match logfiles.add_file(&f).await {
-				Ok(_) => (),
+		if syslog_enabled && opt.syslog_panes == SyslogPanes::Single {
+			let name = SYSLOG_MONITOR_NAME.to_string();
+			logfile_names.push(name.clone());
+			monitors.insert(name.clone(), new_monitor(&opt, &name));
+			if name_for_focus.is_empty() {
+				name_for_focus = name;
+			}
+		}
+
+		let replay = match opt.replay {
+			true => match Replay::load(&opt) {
+				Ok(replay) => Some(replay),
 				Err(e) => {
-					println!("ERROR: {}", e);
-					println!(
-						"Note: it is ok for the file not to exist, but the file's parent directory must exist."
-					);
+					println!("ERROR: unable to load logfiles for replay: {}", e);
 					return Err(e);
 				}
-			}
-		}
+			},
+			false => None,
+		};
 
 		let mut app = App {
 			opt,
@@ -84,31 +241,387 @@ impl App {
 			logfile_with_focus: name_for_focus.clone(),
 			logfiles,
 			logfile_names,
+			sources,
+			keymap,
+			replay,
+			opened: Vec::new(),
+			closed: SourceIndex::new(),
+			closed_syslog_panes: Vec::new(),
 		};
 		app.set_logfile_focus(&name_for_focus);
 		Ok(app)
 	}
 
-	pub fn get_monitor_for_file_path(&mut self, logfile: &String) -> Option<(&mut LogMonitor)> {
-		let mut index = 0;
-		let mut monitor_for_path = None;
-		for (monitor_file, mut monitor) in self.monitors.iter_mut() {
-			if monitor_file.eq(logfile) {
-				monitor_for_path = Some(monitor);
-				break;
+	/// Watch the logfiles for new lines (unless they are being replayed)
+	pub async fn follow_logfiles(&mut self) -> Result<(), std::io::Error> {
+		if self.opt.replay {
+			return Ok(());
+		}
+
+		for f in &self.opt.files {
+			match self.logfiles.add_file(&f).await {
+				Ok(path) => {
+					self.sources.add_alias(&path, f);
+					self.dash_state.diagnostic(&format!("following {}", f));
+				}
+				Err(e) => {
+					println!("ERROR: {}", e);
+					println!(
+						"Note: it is ok for the file not to exist, but the file's parent directory must exist."
+					);
+					return Err(e);
+				}
 			}
-			use std::env::current_dir;
-			use std::path::Path;
-			if let Ok(current_dir) = current_dir() {
-				let logfile_path = Path::new(logfile.as_str());
-				if current_dir.join(monitor_file).eq(&logfile_path) {
-					monitor_for_path = Some(monitor);
-					break;
+		}
+		Ok(())
+	}
+
+	/// Follow logfiles opened while running
+	pub async fn update_followed_logfiles(&mut self) {
+		for f in std::mem::take(&mut self.opened) {
+			match self.logfiles.add_file(&f).await {
+				Ok(path) => {
+					self.sources.add_alias(&path, &f);
+					self.dash_state.diagnostic(&format!("following {}", f));
+				}
+				Err(e) => {
+					let message = format!("unable to follow {}: {}", f, e);
+					self.dash_state.diagnostic(&message);
+					self.dash_state.message = Some(Err(message));
 				}
 			}
-			index += 1;
 		}
-		return monitor_for_path;
+	}
+
+	/// Add a pane for a logfile and load its last lines
+	///
+	/// The logfile is followed for new lines from the next call to
+	/// update_followed_logfiles().
+	pub fn open_logfile(&mut self, f: &str) -> Result<(), String> {
+		if self.opt.replay {
+			return Err(String::from("logfiles can't be opened while replaying"));
+		}
+		if self.monitors.contains_key(f) {
+			self.set_logfile_focus(&f.to_string());
+			return Err(format!("{} is already open", f));
+		}
+
+		let mut monitor = new_monitor(&self.opt, f);
+		monitor.time_window = self.dash_state.active_time_window();
+		monitor
+			.load_logfile()
+			.map_err(|e| format!("unable to load {}: {}", f, e))?;
+		// A logfile closed earlier is still followed
+		let followed = match self.closed.lookup(Path::new(f)) {
+			Some(closed) => {
+				self.closed.remove(&closed);
+				true
+			}
+			None => false,
+		};
+		if let Some(same) = self.sources.add(f) {
+			self.dash_state
+				.diagnostic(&format!("{} is the same file as {}", f, same));
+		}
+		self.dash_state
+			.diagnostic(&format!("loaded {} lines from {}", monitor.lines_seen, f));
+
+		self.opt.files.push(f.to_string());
+		self.logfile_names.push(f.to_string());
+		self.monitors.insert(f.to_string(), monitor);
+		if !followed {
+			self.opened.push(f.to_string());
+		}
+		self.set_logfile_focus(&f.to_string());
+		Ok(())
+	}
+
+	/// Remove the pane of a logfile or of syslog messages
+	///
+	/// linemux can't stop following a file, so lines from a closed logfile
+	/// are dropped as they arrive. Messages for a closed syslog pane are
+	/// dropped too, rather than opening the pane again. If the pane had
+	/// focus, focus moves to the pane which takes its place.
+	pub fn close_logfile(&mut self, name: &str) -> Result<(), String> {
+		let position = match self.logfile_names.iter().position(|f| f == name) {
+			Some(position) => position,
+			None => return Err(format!("{} is not open", name)),
+		};
+
+		if self.logfile_with_focus == name {
+			self.set_logfile_focus(&String::new());
+		}
+		self.monitors.remove(name);
+		self.logfile_names.remove(position);
+		match self.opt.files.iter().position(|f| f == name) {
+			Some(file) => {
+				self.opt.files.remove(file);
+				self.sources.remove(name);
+				// Those opened since the last update aren't followed yet
+				let followed = !self.opened.iter().any(|f| f == name);
+				self.opened.retain(|f| f != name);
+				if followed {
+					self.closed.add(name);
+				}
+			}
+			None => self.closed_syslog_panes.push(name.to_string()),
+		}
+
+		if self.logfile_with_focus.is_empty() && !self.logfile_names.is_empty() {
+			let next = self.logfile_names[position.min(self.logfile_names.len() - 1)].clone();
+			self.set_logfile_focus(&next);
+		}
+		self.dash_state.dirty = true;
+		Ok(())
+	}
+
+	/// Remove the pane with focus, returning the name of its logfile
+	pub fn close_logfile_with_focus(&mut self) -> Result<String, String> {
+		let name = self.logfile_with_focus.clone();
+		if !self.monitors.contains_key(&name) {
+			return Err(String::from("no logfile has focus"));
+		}
+		self.close_logfile(&name)?;
+		Ok(name)
+	}
+
+	/// Show the file picker, starting in the directory of the logfile with focus
+	pub fn open_file_picker(&mut self) {
+		let dir = Path::new(&self.logfile_with_focus)
+			.parent()
+			.filter(|dir| dir.is_dir())
+			.map(Path::to_path_buf)
+			.or_else(|| std::env::current_dir().ok())
+			.unwrap_or_else(|| PathBuf::from("/"));
+		let dir = absolute_path(&dir).unwrap_or(dir);
+		self.dash_state.file_picker = Some(FilePicker::new(&dir));
+	}
+
+	/// Start listening for syslog messages if requested on the command line
+	pub fn start_syslog_receiver(&self) -> Result<Option<SyslogReceiver>, std::io::Error> {
+		if self.opt.syslog_udp.is_none() && self.opt.syslog_tcp.is_none() {
+			return Ok(None);
+		}
+
+		match SyslogReceiver::new(
+			self.opt.syslog_udp.as_deref(),
+			self.opt.syslog_tcp.as_deref(),
+		) {
+			Ok(receiver) => Ok(Some(receiver)),
+			Err(e) => {
+				println!("ERROR: unable to listen for syslog messages: {}", e);
+				Err(e)
+			}
+		}
+	}
+
+	/// Called when the timer wakes the event loop, to release any replayed
+	/// lines now due, note changes to logfiles and continue scanning history
+	pub fn on_tick(&mut self) {
+		if let Some(replay) = self.replay.as_mut() {
+			append_replay_lines(&mut self.monitors, replay.tick());
+			let status = Some(replay.status());
+			if status != self.dash_state.replay_status {
+				self.dash_state.replay_status = status;
+				self.dash_state.dirty = true;
+			}
+		} else {
+			for f in self.opt.files.iter() {
+				if let Some(event) = self
+					.monitors
+					.get_mut(f)
+					.and_then(|monitor| monitor.check_logfile())
+				{
+					self.dash_state.diagnostic(&event);
+				}
+			}
+		}
+
+		// Scan the history of one logfile at a time, so ticks stay short
+		let monitors = &mut self.monitors;
+		let scanning = self.opt.files.iter().find(|f| {
+			monitors
+				.get(*f)
+				.is_some_and(|monitor| monitor.scan.is_some())
+		});
+		if let Some(monitor) = scanning.and_then(|f| monitors.get_mut(f)) {
+			if let Some(note) = monitor.scan_history(SCAN_TIME_PER_TICK) {
+				self.dash_state.diagnostic(&note);
+			}
+		}
+	}
+
+	/// True if the dashboard needs drawing now
+	///
+	/// It is drawn at once when the terminal has been resized. Otherwise
+	/// changes are drawn at most --max-fps times a second, and time-dependent
+	/// elements (activity strips, ages, --last) are moved on every second.
+	pub fn needs_redraw(&self, size: Rect) -> bool {
+		match self.dash_state.last_drawn {
+			None => true,
+			Some((drawn_size, time)) => {
+				drawn_size != size
+					|| (self.has_changes() && time.elapsed() >= self.frame_interval())
+					|| (self.shows_time() && time.elapsed() >= IDLE_REDRAW_INTERVAL)
+			}
+		}
+	}
+
+	/// When the event loop should next wake if nothing else happens, or
+	/// None if it need not wake until a line arrives or a key is pressed
+	pub fn next_wakeup(&self) -> Option<Instant> {
+		let now = Instant::now();
+		let drawn = self.dash_state.last_drawn.map(|(_, time)| time);
+		let tick = Duration::from_millis(self.opt.tick_rate);
+		let replaying = self
+			.replay
+			.as_ref()
+			.is_some_and(|replay| !replay.is_finished() && !replay.paused);
+		let scanning = self.monitors.values().any(|monitor| monitor.scan.is_some());
+
+		let mut due = Vec::new();
+		if self.has_changes() {
+			due.push(drawn.map_or(now, |time| time + self.frame_interval()));
+		}
+		if self.shows_time() {
+			due.push(drawn.map_or(now, |time| time + IDLE_REDRAW_INTERVAL));
+		}
+		if replaying || scanning {
+			due.push(now + tick);
+		}
+		if self.dash_state.debug_window && self.replay.is_none() {
+			due.push(now + IDLE_REDRAW_INTERVAL); // To note logfile rotation
+		}
+		due.into_iter().min()
+	}
+
+	/// Shortest time between frames, from --max-fps
+	pub fn frame_interval(&self) -> Duration {
+		Duration::from_secs(1) / self.opt.max_fps.max(1)
+	}
+
+	/// True if anything shown has changed since the dashboard was drawn
+	fn has_changes(&self) -> bool {
+		self.dash_state.dirty || self.monitors.values().any(|monitor| monitor.dirty)
+	}
+
+	/// True if anything shown changes with the passing of time alone
+	fn shows_time(&self) -> bool {
+		self.dash_state.gutter == Gutter::Relative
+			|| matches!(
+				self.dash_state.active_time_window(),
+				Some(TimeWindow::Last(_))
+			) || self
+			.monitors
+			.values()
+			.any(|monitor| !monitor.activity.is_idle())
+	}
+
+	/// Note that the dashboard has just been drawn at the given size
+	pub fn mark_drawn(&mut self, size: Rect) {
+		self.dash_state.dirty = false;
+		self.dash_state.last_drawn = Some((size, Instant::now()));
+		for monitor in self.monitors.values_mut() {
+			monitor.dirty = false;
+		}
+	}
+
+	/// Read the new lines of a logfile the watcher has read a line from
+	///
+	/// The monitor reads the lines itself, decoding them with the logfile's
+	/// encoding, so the watcher's line only says which logfile has changed.
+	/// Lines from closed logfiles, which are still followed, are dropped quietly.
+	pub fn read_logfile_lines(&mut self, source: &String) {
+		if let Some(monitor) = self.get_monitor_for_file_path(source) {
+			if let Err(e) = monitor.read_new_lines() {
+				let message = format!("unable to read {}: {}", monitor.logfile, e);
+				self.dash_state.diagnostic(&message);
+			}
+		} else if self.closed.lookup(Path::new(source)).is_none() {
+			self.dash_state
+				.diagnostic(&format!("dropped line from unknown file {}", source));
+		}
+	}
+
+	/// Read the new lines of every logfile
+	///
+	/// Used when the watcher fails to read a line, such as one which isn't
+	/// valid UTF-8, as it doesn't say which logfile the line was in.
+	pub fn read_all_logfile_lines(&mut self) {
+		for f in self.opt.files.clone().iter() {
+			self.read_logfile_lines(f);
+		}
+	}
+
+	/// Control the replay, if there is one
+	pub fn handle_replay_action(&mut self, action: Action) {
+		let replay = match self.replay.as_mut() {
+			Some(replay) => replay,
+			None => return,
+		};
+		match action {
+			Action::ReplayPause => replay.paused = !replay.paused,
+			Action::ReplayStep => {
+				replay.paused = true;
+				append_replay_lines(&mut self.monitors, replay.step());
+			}
+			Action::ReplaySeekForward => {
+				append_replay_lines(&mut self.monitors, replay.seek_forward(REPLAY_SEEK_STEP));
+			}
+			Action::ReplaySeekBack => {
+				replay.seek_back(REPLAY_SEEK_STEP);
+				for monitor in self.monitors.values_mut() {
+					monitor.clear();
+				}
+				append_replay_lines(&mut self.monitors, replay.release_due());
+			}
+			Action::ReplayFaster => replay.set_speed(replay.speed * 2.0),
+			Action::ReplaySlower => replay.set_speed(replay.speed / 2.0),
+			_ => (),
+		}
+		// Lines released by seeking aren't activity
+		if action == Action::ReplaySeekForward || action == Action::ReplaySeekBack {
+			for monitor in self.monitors.values_mut() {
+				monitor.activity.clear();
+			}
+		}
+		self.dash_state.replay_status = Some(replay.status());
+	}
+
+	/// Append a received syslog message to its monitor, creating
+	/// the monitor if this is the first message for its pane
+	pub fn append_syslog_message(&mut self, message: &SyslogMessage) {
+		let pane = match self.opt.syslog_panes {
+			SyslogPanes::Single => None,
+			SyslogPanes::Host => message.hostname.as_ref(),
+			SyslogPanes::App => message.app_name.as_ref(),
+		};
+		let name = match pane {
+			Some(pane) => format!("{}:{}", SYSLOG_MONITOR_NAME, pane),
+			None => SYSLOG_MONITOR_NAME.to_string(),
+		};
+
+		if self.closed_syslog_panes.contains(&name) {
+			return;
+		}
+		if !self.monitors.contains_key(&name) {
+			let mut monitor = new_monitor(&self.opt, &name);
+			monitor.time_window = self.dash_state.active_time_window();
+			self.logfile_names.push(name.clone());
+			self.monitors.insert(name.clone(), monitor);
+			if self.logfile_with_focus.is_empty() {
+				self.set_logfile_focus(&name);
+			}
+		}
+
+		if let Some(monitor) = self.monitors.get_mut(&name) {
+			monitor.append_to_content(&message.to_line());
+		}
+	}
+
+	pub fn get_monitor_for_file_path(&mut self, logfile: &String) -> Option<&mut LogMonitor> {
+		let name = self.sources.lookup(Path::new(logfile))?;
+		self.monitors.get_mut(&name)
 	}
 
 	pub fn get_monitor_with_focus(&mut self) -> Option<(&mut LogMonitor)> {
@@ -142,6 +655,10 @@ impl App {
 	}
 
 	pub fn change_focus_next(&mut self) {
+		if self.logfile_names.is_empty() {
+			return;
+		}
+
 		let mut next_i = 0;
 		for (i, name) in self.logfile_names.iter().enumerate() {
 			if name == &self.logfile_with_focus {
@@ -152,11 +669,7 @@ impl App {
 			}
 		}
 
-		if false	// In case --debug_window is implemented (see vdash)
-			&& next_i == 0 
-			&& self.opt.debug_window 
-			&& self.logfile_with_focus != DEBUG_WINDOW_NAME
-		{
+		if next_i == 0 && self.opt.debug_window && self.logfile_with_focus != DEBUG_WINDOW_NAME {
 			self.set_logfile_focus(&DEBUG_WINDOW_NAME.to_string());
 			return;
 		}
@@ -166,6 +679,10 @@ impl App {
 	}
 
 	pub fn change_focus_previous(&mut self) {
+		if self.logfile_names.is_empty() {
+			return;
+		}
+
 		let len = self.logfile_names.len();
 		let mut previous_i = len - 1;
 		let mut log = String::from("change_previous()           \r\n");
@@ -179,8 +696,7 @@ impl App {
 				break;
 			}
 		}
-		if false	// In case --debug_window is implemented (see vdash)
-			&& self.opt.debug_window
+		if self.opt.debug_window
 			&& previous_i == len - 1
 			&& self.logfile_with_focus != DEBUG_WINDOW_NAME
 		{
@@ -191,9 +707,139 @@ impl App {
 		self.set_logfile_focus(&new_focus_name);
 	}
 
+	/// Handle a key press: pass it to the file picker or command line if
+	/// active, or else return the action bound to the key (if it completes a
+	/// key sequence)
+	pub fn handle_key(&mut self, key: &str) -> Option<Action> {
+		if self.dash_state.message.take().is_some() {
+			self.dash_state.dirty = true;
+		}
+		if let Some(picker) = self.dash_state.file_picker.as_mut() {
+			self.dash_state.dirty = true;
+			match picker.handle_key(key) {
+				PickerOutcome::Continue => (),
+				PickerOutcome::Cancel => self.dash_state.file_picker = None,
+				PickerOutcome::Open(path) => {
+					self.dash_state.file_picker = None;
+					self.run_command(&format!("open {}", path.to_string_lossy()));
+				}
+			}
+			return None;
+		}
+		if !self.dash_state.command_line.active {
+			return self.keymap.handle_key(key);
+		}
+
+		self.dash_state.dirty = true;
+		if let Some(input) = self.dash_state.command_line.handle_key(key) {
+			self.run_command(&input);
+		}
+		None
+	}
+
+	/// Run a command typed after ':', showing the outcome in the status bar
+	pub fn run_command(&mut self, input: &str) {
+		self.dash_state.dirty = true;
+		match input.parse::<Command>().and_then(|command| command.run(self)) {
+			Ok(message) if message.is_empty() => (),
+			result => self.dash_state.message = Some(result),
+		}
+	}
+
+	/// Perform an action other than Action::Quit, which is handled by the caller
+	pub fn do_action(&mut self, action: Action) {
+		self.dash_state.dirty = true;
+		match action {
+			Action::Quit => (),
+			Action::FocusNext => self.change_focus_next(),
+			Action::FocusPrevious => self.change_focus_previous(),
+			Action::ScrollUp => self.handle_arrow_up(),
+			Action::ScrollDown => self.handle_arrow_down(),
+			Action::ScrollPageUp => self.handle_scroll(ScrollTo::PageUp),
+			Action::ScrollPageDown => self.handle_scroll(ScrollTo::PageDown),
+			Action::ScrollTop => self.handle_scroll(ScrollTo::Top),
+			Action::ScrollBottom => self.handle_scroll(ScrollTo::Bottom),
+			Action::LayoutHorizontal => self.dash_state.main_view = DashViewMain::DashHorizontal,
+			Action::LayoutVertical => self.dash_state.main_view = DashViewMain::DashVertical,
+			Action::ToggleExpand => self.handle_toggle_expand(),
+			Action::ToggleTemplates => {
+				if let Some(monitor) = self.get_monitor_with_focus() {
+					monitor.toggle_templates_view();
+				}
+			}
+			Action::ToggleTimeWindow => {
+				self.dash_state.time_window_enabled = !self.dash_state.time_window_enabled;
+				let time_window = self.dash_state.active_time_window();
+				for monitor in self.monitors.values_mut() {
+					monitor.time_window = time_window;
+				}
+			}
+			Action::ToggleGutter => {
+				self.dash_state.gutter = match self.dash_state.gutter {
+					Gutter::Off => Gutter::Absolute,
+					Gutter::Absolute => Gutter::Relative,
+					Gutter::Relative => Gutter::Off,
+				}
+			}
+			Action::ToggleHighlights => {
+				self.dash_state.highlighter.enabled = !self.dash_state.highlighter.enabled;
+			}
+			Action::ReplayPause
+			| Action::ReplayStep
+			| Action::ReplaySeekForward
+			| Action::ReplaySeekBack
+			| Action::ReplayFaster
+			| Action::ReplaySlower => self.handle_replay_action(action),
+			Action::ClearFilter => {
+				if let Some(monitor) = self.get_monitor_with_focus() {
+					monitor.clear_filter();
+				}
+			}
+			Action::CommandMode => self.dash_state.command_line.start(),
+			Action::OpenFilePicker => self.open_file_picker(),
+			Action::ClosePane => {
+				self.dash_state.message = Some(
+					self.close_logfile_with_focus()
+						.map(|name| format!("closed {}", name)),
+				);
+			}
+			Action::ToggleLayout => {
+				self.dash_state.main_view = match self.dash_state.main_view {
+					DashViewMain::DashHorizontal => DashViewMain::DashVertical,
+					DashViewMain::DashVertical => DashViewMain::DashHorizontal,
+				}
+			}
+		}
+	}
+
+	/// Expand or collapse the selected entry if it stands for repeated lines
+	///
+	/// In the templates view, show only the lines matching the selected template.
+	pub fn handle_toggle_expand(&mut self) {
+		if let Some(monitor) = self.get_monitor_with_focus() {
+			if monitor.view == MonitorView::Templates {
+				monitor.filter_by_selected_template();
+			} else if let Some(selected) = monitor.content.state.selected() {
+				if let Some(line) = monitor.content.items.get_mut(selected) {
+					if let Some(repeats) = &mut line.repeats {
+						repeats.expanded = !repeats.expanded;
+					}
+				}
+			}
+		}
+	}
+
+	pub fn handle_scroll(&mut self, to: ScrollTo) {
+		if let Some(monitor) = self.get_monitor_with_focus() {
+			monitor.scroll(to);
+		} else if self.opt.debug_window {
+			do_scroll(&mut self.dash_state.debug_window_list, to, 10);
+		}
+	}
+
 	pub fn handle_arrow_up(&mut self) {
 		if let Some(monitor) = self.get_monitor_with_focus() {
-			do_bracketed_next_previous(&mut monitor.content, false);
+			monitor.scroll(ScrollTo::LineUp);
 		} else if self.opt.debug_window {
 			do_bracketed_next_previous(&mut self.dash_state.debug_window_list, false);
 		}
@@ -201,15 +847,34 @@ impl App {
 
 	pub fn handle_arrow_down(&mut self) {
 		if let Some(monitor) = self.get_monitor_with_focus() {
-			do_bracketed_next_previous(&mut monitor.content, true);
+			monitor.scroll(ScrollTo::LineDown);
 		} else if self.opt.debug_window {
 			do_bracketed_next_previous(&mut self.dash_state.debug_window_list, true);
 		}
 	}
 }
 
+/// Create a monitor with settings from the command line
+fn new_monitor(opt: &Opt, name: &str) -> LogMonitor {
+	let mut monitor = LogMonitor::new(name.to_string(), opt.lines_max);
+	monitor.collapse = opt.collapse;
+	monitor.encoding = opt.encoding_for(name);
+	monitor.full_scan = opt.full_scan;
+	monitor.time_window = TimeWindow::from_opt(opt);
+	monitor
+}
+
+/// Append replayed lines to the monitors of their logfiles
+fn append_replay_lines(monitors: &mut HashMap<String, LogMonitor>, lines: &[ReplayLine]) {
+	for line in lines {
+		if let Some(monitor) = monitors.get_mut(&line.source) {
+			monitor.append_to_content(&line.text);
+		}
+	}
+}
+
 /// Move selection forward or back without wrapping at start or end
-fn do_bracketed_next_previous(list: &mut StatefulList<String>, next: bool) {
+fn do_bracketed_next_previous<T>(list: &mut StatefulList<T>, next: bool) {
 	if (next) {
 		if let Some(selected) = list.state.selected() {
 			if selected != list.items.len() - 1 {
@@ -229,13 +894,273 @@ fn do_bracketed_next_previous(list: &mut StatefulList<String>, next: bool) {
 	}
 }
 
+pub enum ScrollTo {
+	LineUp,
+	LineDown,
+	PageUp,
+	PageDown,
+	Top,
+	Bottom,
+}
+
+/// A position moved as for ScrollTo, within 0..=last
+fn step_position(position: usize, last: usize, to: ScrollTo, page: usize) -> usize {
+	match to {
+		ScrollTo::LineUp => position.saturating_sub(1),
+		ScrollTo::LineDown => (position + 1).min(last),
+		ScrollTo::PageUp => position.saturating_sub(page),
+		ScrollTo::PageDown => (position + page).min(last),
+		ScrollTo::Top => 0,
+		ScrollTo::Bottom => last,
+	}
+}
+
+/// Move selection by a page or to the start or end, without wrapping
+fn do_scroll<T>(list: &mut StatefulList<T>, to: ScrollTo, page: usize) {
+	if list.items.is_empty() {
+		return;
+	}
+
+	let last = list.items.len() - 1;
+	let selected = list.state.selected().unwrap_or(last);
+	list.state
+		.select(Some(step_position(selected, last, to, page.max(1))));
+}
+
+/// Maximum number of repeats remembered by a LogLine (more are only counted)
+pub const MAX_REMEMBERED_REPEATS: usize = 100;
+
+/// A line of content, which may stand for several consecutive repeated lines
+///
+/// Each line's text is a separate allocation, held with 64 bytes of fields.
+/// Most lines are never repeated, so what repeats need is only allocated
+/// for those that are.
+pub struct LogLine {
+	pub text: Box<str>,                // Boxed, as it never grows
+	pub repeats: Option<Box<Repeats>>, // Only for a line that was repeated
+	pub template: Option<u32>,         // Id of the template the line matches
+	pub timestamp: Option<SystemTime>, // When logged, if the line says
+	pub arrived: Option<SystemTime>,   // When received, if known
+}
+
+/// The repeats of a line
+pub struct Repeats {
+	pub count: usize,      // Number of repeats after the first line
+	pub kept: Vec<Repeat>, // The most recent repeats, oldest first
+	pub expanded: bool,    // Show the repeats as well as the first line
+}
+
+/// When a repeat arrived, and its text if different from the first line
+pub struct Repeat {
+	pub time: Option<SystemTime>,
+	pub text: Option<Box<str>>,
+}
+
+impl LogLine {
+	pub fn new(text: &str, arrived: Option<SystemTime>) -> LogLine {
+		LogLine {
+			text: text.into(),
+			repeats: None,
+			template: None,
+			timestamp: parse_line_timestamp(text),
+			arrived,
+		}
+	}
+
+	/// The text of the line and of each repeat kept, oldest first
+	///
+	/// Only the most recent repeats are kept, so any earlier ones are stood
+	/// for by a note of how many there were.
+	pub fn texts(&self) -> impl Iterator<Item = Cow<'_, str>> {
+		let kept = self
+			.repeats
+			.as_ref()
+			.map_or(&[][..], |repeats| &repeats.kept);
+		let unkept = match self.count() - 1 - kept.len() {
+			0 => None,
+			unkept => Some(Cow::Owned(format!("({} earlier repeats not kept)", unkept))),
+		};
+		let repeats = kept
+			.iter()
+			.map(move |repeat| Cow::Borrowed(repeat.text.as_deref().unwrap_or(&self.text)));
+		std::iter::once(Cow::Borrowed(&*self.text))
+			.chain(unkept)
+			.chain(repeats)
+	}
+
+	/// When the line was logged, or if it doesn't say, when it arrived
+	pub fn time(&self) -> Option<SystemTime> {
+		self.timestamp.or(self.arrived)
+	}
+
+	/// Number of lines this entry stands for
+	pub fn count(&self) -> usize {
+		self.repeats.as_ref().map_or(0, |repeats| repeats.count) + 1
+	}
+
+	/// True if the line was repeated and its repeats are to be shown
+	pub fn is_expanded(&self) -> bool {
+		matches!(&self.repeats, Some(repeats) if repeats.expanded)
+	}
+
+	/// When the most recent repeat arrived
+	pub fn last_repeat(&self) -> Option<SystemTime> {
+		let repeats = self.repeats.as_ref()?;
+		repeats.kept.last().and_then(|repeat| repeat.time)
+	}
+
+	/// True if text repeats this line, either exactly or in all but its numbers
+	pub fn is_repeated_by(&self, text: &str, collapse: Collapse) -> bool {
+		match collapse {
+			Collapse::None => false,
+			Collapse::Exact => *self.text == *text,
+			Collapse::Similar => {
+				*self.text == *text || mask_numbers(&self.text) == mask_numbers(text)
+			}
+		}
+	}
+
+	pub fn add_repeat(&mut self, text: &str, arrived: Option<SystemTime>) {
+		let text = match *self.text == *text {
+			true => None,
+			false => Some(text.into()),
+		};
+		let repeats = self.repeats.get_or_insert_with(|| {
+			Box::new(Repeats {
+				count: 0,
+				kept: Vec::new(),
+				expanded: false,
+			})
+		});
+		repeats.count += 1;
+		repeats.kept.push(Repeat {
+			time: arrived,
+			text,
+		});
+		if repeats.kept.len() > MAX_REMEMBERED_REPEATS {
+			repeats.kept.remove(0);
+		}
+	}
+
+	/// Approximate memory used, in bytes
+	pub fn bytes(&self) -> usize {
+		let repeats = self.repeats.as_ref().map_or(0, |repeats| {
+			let texts: usize = repeats
+				.kept
+				.iter()
+				.map(|repeat| repeat.text.as_ref().map_or(0, |text| text.len()))
+				.sum();
+			std::mem::size_of::<Repeats>()
+				+ texts + repeats.kept.capacity() * std::mem::size_of::<Repeat>()
+		});
+		std::mem::size_of::<LogLine>() + self.text.len() + repeats
+	}
+}
+
+/// Replace each run of digits with a single '0', so that lines which differ
+/// only in numbers, timestamps or IDs compare equal
+fn mask_numbers(text: &str) -> String {
+	let mut masked = String::with_capacity(text.len());
+	let mut in_number = false;
+	for c in text.chars() {
+		if c.is_ascii_digit() {
+			if !in_number {
+				masked.push('0');
+			}
+			in_number = true;
+		} else {
+			masked.push(c);
+			in_number = false;
+		}
+	}
+	masked
+}
+
 pub struct LogMonitor {
 	pub index: usize,
-	pub content: StatefulList<String>,
+	pub content: RingList<LogLine>, // Holds up to --lines-max lines
 	pub has_focus: bool,
 	pub logfile: String,
+	pub activity: Activity,
+	pub lines_seen: usize,
+	pub visible_lines: usize, // Lines visible when last drawn
+	pub collapse: Collapse,
+	pub view: MonitorView,
+	pub templates: TemplateMiner,
+	pub templates_state: ListState,
+	pub template_filter: Option<usize>, // Only show lines matching this template
+	pub line_filter: Option<Regex>,     // Only show lines matching this (see :filter)
+	pub time_window: Option<TimeWindow>, // Only show lines from this time window
+	pub logfile_state: Option<Option<LogfileState>>, // When last checked (None until then)
+	pub encoding: Encoding,
+	pub full_scan: bool,    // Scan the history of the logfile after loading
+	pub scan: Option<Scan>, // While scanning
+	pub scanned_lines: usize,
+	follow: Option<Follow>, // Reading lines added since the logfile was loaded
+	pub view_offset: usize, // Position of the first line in view, when drawn
+	pub dirty: bool,        // Changed since last drawn
+	line_bytes: usize,      // Memory used by the lines in content
+}
 
-	max_content: usize, // Limit number of lines in content
+/// Size and identity of a logfile, used to notice rotation
+pub struct LogfileState {
+	pub size: u64,
+	pub id: Option<u64>, // Inode, where supported
+}
+
+impl LogfileState {
+	/// The state of a logfile, or None if it doesn't exist
+	pub fn of(path: &str) -> Option<LogfileState> {
+		let metadata = std::fs::metadata(path).ok()?;
+		Some(LogfileState {
+			size: metadata.len(),
+			id: file_id(&metadata),
+		})
+	}
+}
+
+/// Similarity needed for a line to match a template (fraction of tokens equal)
+pub const TEMPLATE_SIMILARITY: f64 = 0.5;
+
+/// Maximum number of templates mined for each LogMonitor
+pub const MAX_TEMPLATES: usize = 1000;
+
+/// Limits the lines shown to those logged within a period of time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeWindow {
+	Last(Duration),                                // Up to now, so lines age out
+	Range(Option<SystemTime>, Option<SystemTime>), // From and to, either may be open
+}
+
+impl TimeWindow {
+	/// The time window given on the command line, if any
+	pub fn from_opt(opt: &Opt) -> Option<TimeWindow> {
+		match (opt.last, opt.since, opt.until) {
+			(Some(last), _, _) => Some(TimeWindow::Last(last)),
+			(None, None, None) => None,
+			(None, since, until) => Some(TimeWindow::Range(since, until)),
+		}
+	}
+
+	pub fn contains(&self, time: SystemTime) -> bool {
+		match self {
+			TimeWindow::Last(duration) => match SystemTime::now().duration_since(time) {
+				Ok(age) => age <= *duration,
+				Err(_) => true, // In the future
+			},
+			// Neither before the start nor after the end
+			TimeWindow::Range(from, to) => {
+				!matches!(from, Some(from) if time < *from) && !matches!(to, Some(to) if time > *to)
+			}
+		}
+	}
+}
+
+/// What a LogMonitor shows: its lines, or the templates they match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MonitorView {
+	Lines,
+	Templates,
 }
 
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -248,57 +1173,623 @@ impl LogMonitor {
 			index,
 			has_focus: false,
 			logfile: f,
-			max_content: max_lines,
-			content: StatefulList::with_items(vec![]),
+			content: RingList::new(max_lines),
+			activity: Activity::default(),
+			lines_seen: 0,
+			visible_lines: 0,
+			collapse: Collapse::None,
+			view: MonitorView::Lines,
+			templates: TemplateMiner::new(TEMPLATE_SIMILARITY, MAX_TEMPLATES),
+			templates_state: ListState::default(),
+			template_filter: None,
+			line_filter: None,
+			time_window: None,
+			logfile_state: None,
+			encoding: Encoding::Utf8,
+			full_scan: false,
+			scan: None,
+			scanned_lines: 0,
+			follow: None,
+			view_offset: 0,
+			dirty: true,
+			line_bytes: 0,
 		}
 	}
 
+	/// Load the last lines of the logfile, seeking back from its end
+	///
+	/// With full_scan set, the rest of the logfile is then read a little at
+	/// a time by scan_history().
 	pub fn load_logfile(&mut self) -> std::io::Result<()> {
-		use std::io::{BufRead, BufReader};
-
-		let f = File::open(self.logfile.to_string());
-		let f = match f {
+		let path = Path::new(&self.logfile);
+		let mut file = match File::open(path) {
 			Ok(file) => file,
-			Err(_e) => return Ok(()), // It's ok for a logfile not to exist yet
+			Err(_e) => {
+				// It's ok for a logfile not to exist yet
+				self.follow = Some(Follow::new(path, 0, self.encoding.newline()));
+				return Ok(());
+			}
 		};
 
-		let f = BufReader::new(f);
+		// Lines already in the file arrived no later than its last change
+		let metadata = file.metadata()?;
+		let arrived = metadata.modified().ok();
 
-		for line in f.lines() {
-			let line = line.expect("Unable to read line");
-			self.process_line(&line);
+		let head = read_head(&mut file, BINARY_SAMPLE)?;
+		self.encoding = self.encoding.for_content(&head);
+		let (lines, start, end) = match is_binary(&head, self.encoding) {
+			true => (hexdump_summary(&head, metadata.len()), 0, metadata.len()),
+			false => {
+				let newline = self.encoding.newline();
+				let (tail, start) = read_last_lines(&mut file, self.content.capacity(), newline)?;
+				let lines = decode(&tail, self.encoding)
+					.lines()
+					.map(String::from)
+					.collect();
+				(lines, start, start + tail.len() as u64)
+			}
+		};
+		for line in lines {
+			self.process_line(&line, arrived);
 		}
+		let newline = self.encoding.newline();
+		self.follow = Some(Follow::new(Path::new(&self.logfile), end, newline));
+		if self.full_scan && start > 0 {
+			self.scan = Some(Scan::new(file, start, self.encoding.newline())?);
+		}
+		// Existing content isn't activity
+		self.activity.clear();
 
 		Ok(())
 	}
 
-	pub fn process_line(&mut self, text: &str) {
+	/// Load history from rotated logfiles, before the lines already loaded
+	///
+	/// Looks for logfile.1, logfile.2.gz and so on, stopping when enough
+	/// lines have been found to fill the rest of the content, or when the
+	/// files are older than max_age. Each file loaded is preceded by a
+	/// marker line in the style of `tail` so the boundaries are visible.
+	/// Call after load_logfile(), so only the history still wanted is read.
+	pub fn load_rotated_logfiles(&mut self, max_age: Option<Duration>) -> std::io::Result<()> {
+		let mut history: Vec<(PathBuf, Option<SystemTime>, Vec<String>)> = Vec::new();
+		let mut used = self.content.items.len() + 1; // With the marker before them
+
+		for path in rotated_logfile_paths(&self.logfile) {
+			let wanted = self.content.capacity().saturating_sub(used + 1); // And this marker
+			if wanted == 0 {
+				break;
+			}
+			let modified = std::fs::metadata(&path)?.modified().ok();
+			if let (Some(max_age), Some(modified)) = (max_age, modified) {
+				match SystemTime::now().duration_since(modified) {
+					Ok(age) if age > max_age => break,
+					_ => (),
+				}
+			}
+
+			let lines = read_logfile_tail(&path, self.encoding, wanted)?;
+			used += lines.len() + 1;
+			history.push((path, modified, lines));
+		}
+
+		if history.is_empty() {
+			return Ok(());
+		}
+
+		let capacity = self.content.capacity();
+		let loaded = std::mem::replace(&mut self.content, RingList::new(capacity));
+		self.line_bytes = 0;
+		for (path, arrived, lines) in history.iter().rev() {
+			self.append_line(&boundary_marker(&path.to_string_lossy()), *arrived);
+			for line in lines {
+				self.process_line(line, *arrived);
+			}
+		}
+		self.append_line(&boundary_marker(&self.logfile.to_string()), None);
+		for line in loaded.items {
+			self.push_line(line);
+		}
+		self.activity.clear();
+		Ok(())
+	}
+
+	/// Continue reading the logfile's history for up to the time allowed
+	///
+	/// Lines before those loaded count towards lines_seen and the templates
+	/// but aren't added to the content. Returns a note for the debug window
+	/// when the scan ends.
+	pub fn scan_history(&mut self, allowed: Duration) -> Option<String> {
+		let scan = self.scan.as_mut()?;
+		let (encoding, templates) = (self.encoding, &mut self.templates);
+		let mut lines = 0;
+		let scanned = scan.scan_for(allowed, |line| {
+			templates.add(&decode(line, encoding));
+			lines += 1;
+		});
+		self.lines_seen += lines;
+		self.scanned_lines += lines;
+		self.dirty = true; // For the progress shown
+
+		let note = match scanned {
+			Err(e) => format!("scan of {} stopped: {}", self.logfile, e),
+			Ok(()) if scan.is_finished() => {
+				format!(
+					"scanned {} earlier lines of {}",
+					self.scanned_lines, self.logfile
+				)
+			}
+			Ok(()) => return None,
+		};
+		self.scan = None;
+		Some(note)
+	}
+
+	/// Percentage of the logfile's history scanned, while being scanned
+	pub fn scan_percent_done(&self) -> Option<u64> {
+		self.scan.as_ref().map(|scan| scan.percent_done())
+	}
+
+	pub fn process_line(&mut self, text: &str, arrived: Option<SystemTime>) {
 		// TODO parse and update metrics
-		self.append_to_content(text);
+		self.append_line(text, arrived);
+	}
+
+	/// Follow the logfile for lines added from now on, without loading any
+	pub fn skip_existing(&mut self) -> std::io::Result<()> {
+		let end = match File::open(&self.logfile) {
+			Ok(mut file) => {
+				let head = read_head(&mut file, BINARY_SAMPLE)?;
+				self.encoding = self.encoding.for_content(&head);
+				file.metadata()?.len()
+			}
+			Err(_e) => 0,
+		};
+		let newline = self.encoding.newline();
+		self.follow = Some(Follow::new(Path::new(&self.logfile), end, newline));
+		Ok(())
 	}
 
+	/// Append the lines added to the logfile since it was last read
+	///
+	/// The lines are decoded with the logfile's encoding, which is why they
+	/// are read here rather than taken from the watcher.
+	pub fn read_new_lines(&mut self) -> std::io::Result<()> {
+		let mut follow = match self.follow.take() {
+			Some(follow) => follow,
+			None => return Ok(()),
+		};
+		let (encoding, arrived) = (self.encoding, Some(SystemTime::now()));
+		let read = follow.read_lines(|line| self.process_line(&decode(line, encoding), arrived));
+		self.follow = Some(follow);
+		read
+	}
+
+	/// Append a line which has just arrived
 	pub fn append_to_content(&mut self, text: &str) {
-		self.content.items.push(text.to_string());
-		let len = self.content.items.len();
-		if len > self.max_content {
-			self.content.items = self.content.items.split_off(len - self.max_content);
-		} else {
-			self.content.state.select(Some(len - 1));
+		self.append_line(text, Some(SystemTime::now()));
+	}
+
+	/// Append a line which arrived at the given time, if known
+	pub fn append_line(&mut self, text: &str, arrived: Option<SystemTime>) {
+		self.activity.record();
+		self.lines_seen += 1;
+		let template = self.templates.add(text);
+		self.dirty = true;
+		if let Some(last) = self.content.items.back_mut() {
+			if last.is_repeated_by(text, self.collapse) {
+				let bytes = last.bytes();
+				last.add_repeat(text, arrived);
+				self.line_bytes = self.line_bytes + last.bytes() - bytes;
+				return;
+			}
+		}
+
+		let mut line = LogLine::new(text, arrived);
+		line.template = template.map(|template| template as u32); // Below MAX_TEMPLATES
+		self.push_line(line);
+	}
+
+	fn push_line(&mut self, line: LogLine) {
+		self.line_bytes += line.bytes();
+		if let Some(dropped) = self.content.push(line) {
+			self.line_bytes -= dropped.bytes();
 		}
 	}
 
 	fn _reset_metrics(&mut self) {}
+
+	/// Describe any change to the logfile since it was last checked, such as rotation
+	pub fn check_logfile(&mut self) -> Option<String> {
+		let state = LogfileState::of(&self.logfile);
+		let event = match (&self.logfile_state, &state) {
+			(Some(Some(_)), None) => Some("removed"),
+			(Some(None), Some(_)) => Some("created"),
+			(Some(Some(old)), Some(new)) if old.id != new.id => Some("replaced (rotated)"),
+			(Some(Some(old)), Some(new)) if new.size < old.size => Some("truncated"),
+			_ => None,
+		};
+		self.logfile_state = Some(state);
+		event.map(|event| format!("{} {}", self.logfile, event))
+	}
+
+	/// Remove all content, as if the logfile had just been opened
+	pub fn clear(&mut self) {
+		self.content.clear();
+		self.line_bytes = 0;
+		self.dirty = true;
+		self.activity.clear();
+		self.lines_seen = 0;
+		self.templates = TemplateMiner::new(TEMPLATE_SIMILARITY, MAX_TEMPLATES);
+		self.template_filter = None;
+	}
+
+	/// True if the line passes any filter in effect
+	pub fn is_shown(&self, line: &LogLine) -> bool {
+		if let Some(template) = self.template_filter {
+			if line.template != Some(template as u32) {
+				return false;
+			}
+		}
+		if let Some(regex) = &self.line_filter {
+			if !regex.is_match(&plain_text(&line.text)) {
+				return false;
+			}
+		}
+		match self.time_window {
+			Some(window) => {
+				line.time().is_some_and(|time| window.contains(time))
+					|| line.last_repeat().is_some_and(|time| window.contains(time))
+			}
+			None => true,
+		}
+	}
+
+	/// True if any filter is in effect, so some lines may not be shown
+	pub fn is_filtered(&self) -> bool {
+		self.template_filter.is_some() || self.line_filter.is_some() || self.time_window.is_some()
+	}
+
+	/// Indices of the content lines which pass any filter in effect
+	pub fn shown_lines(&self) -> Vec<usize> {
+		(0..self.content.items.len())
+			.filter(|i| self.is_shown(&self.content.items[*i]))
+			.collect()
+	}
+
+	/// Position of the selection within the shown lines
+	///
+	/// If the selected line is filtered out, the next shown line stands in for it.
+	pub fn shown_position(&self, shown: &[usize]) -> Option<usize> {
+		if shown.is_empty() {
+			return None;
+		}
+		let last = shown.len() - 1;
+		match self.content.state.selected() {
+			Some(selected) => Some(shown.iter().position(|i| *i >= selected).unwrap_or(last)),
+			None => Some(last),
+		}
+	}
+
+	/// Move the selection within the current view, without wrapping
+	pub fn scroll(&mut self, to: ScrollTo) {
+		let page = self.visible_lines.max(1);
+		if self.view == MonitorView::Templates {
+			let len = self.templates.templates.len();
+			if len > 0 {
+				let selected = self.templates_state.selected().unwrap_or(len - 1);
+				self.templates_state
+					.select(Some(step_position(selected, len - 1, to, page)));
+			}
+			return;
+		}
+
+		let shown = self.shown_lines();
+		if let Some(position) = self.shown_position(&shown) {
+			let new_position = step_position(position, shown.len() - 1, to, page);
+			self.content.state.select(Some(shown[new_position]));
+		}
+	}
+
+	/// Switch between showing lines and the templates they match
+	pub fn toggle_templates_view(&mut self) {
+		self.view = match self.view {
+			MonitorView::Lines => {
+				let len = self.templates.templates.len();
+				let selected = self.template_filter.or_else(|| len.checked_sub(1));
+				self.templates_state.select(selected);
+				MonitorView::Templates
+			}
+			MonitorView::Templates => MonitorView::Lines,
+		};
+	}
+
+	/// Show only lines matching the selected template, and return to the lines view
+	pub fn filter_by_selected_template(&mut self) {
+		if let Some(selected) = self.templates_state.selected() {
+			self.template_filter = Some(selected);
+			self.content
+				.state
+				.select(self.shown_lines().last().copied());
+		}
+		self.view = MonitorView::Lines;
+	}
+
+	/// Show only lines matching a regular expression, or all lines for None
+	pub fn set_line_filter(&mut self, regex: Option<Regex>) {
+		self.line_filter = regex;
+		self.content
+			.state
+			.select(self.shown_lines().last().copied());
+		self.view = MonitorView::Lines;
+		self.dirty = true;
+	}
+
+	/// Remove any filter, keeping the selected line
+	pub fn clear_filter(&mut self) {
+		self.template_filter = None;
+		self.line_filter = None;
+		self.view = MonitorView::Lines;
+	}
+
+	pub fn max_content(&self) -> usize {
+		self.content.capacity()
+	}
+
+	/// Change the number of lines kept, dropping the oldest if there are too many
+	pub fn set_max_content(&mut self, max_lines: usize) {
+		for dropped in self.content.set_capacity(max_lines) {
+			self.line_bytes -= dropped.bytes();
+		}
+		self.view_offset = self.view_offset.min(self.content.items.len());
+		self.dirty = true;
+	}
+
+	/// True if the selection is on the newest line, so new lines are followed
+	pub fn is_following(&self) -> bool {
+		match self.content.state.selected() {
+			Some(selected) => selected + 1 >= self.content.items.len(),
+			None => true,
+		}
+	}
+
+	/// Approximate memory used to hold the content, in bytes
+	pub fn content_bytes(&self) -> usize {
+		let spare = self.content.items.capacity() - self.content.items.len();
+		self.line_bytes + spare * std::mem::size_of::<LogLine>()
+	}
+
+	/// How many seconds ago the selected line arrived, if it is recent
+	/// enough to be within the activity history
+	pub fn selected_seconds_ago(&self) -> Option<usize> {
+		let selected = self.content.state.selected()?;
+		let lines_after = self
+			.content
+			.items
+			.iter()
+			.skip(selected + 1)
+			.map(|line| line.count())
+			.sum();
+		self.activity.seconds_ago(lines_after)
+	}
+}
+
+/// Number of seconds of line arrival counts kept by each LogMonitor
+pub const ACTIVITY_SECONDS: usize = 600;
+
+/// Lines received per second, for the last ACTIVITY_SECONDS seconds
+#[derive(Default)]
+pub struct Activity {
+	pub counts: VecDeque<u64>, // Newest last
+	second: u64,               // Time of newest count (seconds since UNIX epoch)
+}
+
+impl Activity {
+	/// Count a line arriving now
+	pub fn record(&mut self) {
+		self.roll_forward();
+		if let Some(count) = self.counts.back_mut() {
+			*count += 1;
+		}
+	}
+
+	/// Add zero counts for any seconds since the last line arrived
+	pub fn roll_forward(&mut self) {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |d| d.as_secs());
+		if self.counts.is_empty() {
+			self.second = now;
+			self.counts.push_back(0);
+			return;
+		}
+
+		let elapsed = now.saturating_sub(self.second).min(ACTIVITY_SECONDS as u64);
+		for _ in 0..elapsed {
+			self.counts.push_back(0);
+		}
+		while self.counts.len() > ACTIVITY_SECONDS {
+			self.counts.pop_front();
+		}
+		self.second = self.second.max(now);
+	}
+
+	/// True if no lines arrived in the seconds shown, so the strip is blank
+	pub fn is_idle(&self) -> bool {
+		self.counts.iter().all(|count| *count == 0)
+	}
+
+	/// Average lines per second over the last few complete seconds
+	pub fn lines_per_second(&self) -> f64 {
+		let seconds = 10;
+		let total: u64 = self.counts.iter().rev().skip(1).take(seconds).sum();
+		total as f64 / seconds as f64
+	}
+
+	/// How many seconds ago a line arrived, given the number of lines which arrived after it
+	pub fn seconds_ago(&self, lines_after: usize) -> Option<usize> {
+		let mut lines = 0;
+		for (seconds_ago, count) in self.counts.iter().rev().enumerate() {
+			lines += *count as usize;
+			if lines > lines_after {
+				return Some(seconds_ago);
+			}
+		}
+		None
+	}
+
+	pub fn clear(&mut self) {
+		self.counts.clear();
+	}
 }
 
+/// Rotated versions of a logfile, newest first
+///
+/// Follows the logrotate naming convention of appending .1, .2 etc. and
+/// optionally .gz, stopping at the first number which doesn't exist.
+fn rotated_logfile_paths(logfile: &str) -> Vec<PathBuf> {
+	let mut paths = Vec::new();
+	for n in 1.. {
+		let plain = PathBuf::from(format!("{}.{}", logfile, n));
+		let compressed = PathBuf::from(format!("{}.{}.gz", logfile, n));
+		if plain.is_file() {
+			paths.push(plain);
+		} else if compressed.is_file() {
+			paths.push(compressed);
+		} else {
+			break;
+		}
+	}
+	paths
+}
+
+/// Read the last max_lines lines of a logfile, decompressing it if it ends with .gz
+///
+/// Only the lines kept are held in memory. A plain logfile is read back from
+/// its end, while a compressed one has to be read through from its start.
+/// Binary content is summarised rather than split into lines (see encoding.rs).
+pub fn read_logfile_tail(
+	path: &Path,
+	encoding: Encoding,
+	max_lines: usize,
+) -> std::io::Result<Vec<String>> {
+	use std::io::Read;
+
+	let mut file = File::open(path)?;
+	if !matches!(path.extension(), Some(extension) if extension == "gz") {
+		let head = read_head(&mut file, BINARY_SAMPLE)?;
+		let encoding = encoding.for_content(&head);
+		if is_binary(&head, encoding) {
+			return Ok(hexdump_summary(&head, file.metadata()?.len()));
+		}
+		let (tail, _) = read_last_lines(&mut file, max_lines, encoding.newline())?;
+		return Ok(decode(&tail, encoding).lines().map(String::from).collect());
+	}
+
+	let mut decoder = MultiGzDecoder::new(file);
+	let mut head = Vec::new();
+	(&mut decoder)
+		.take(BINARY_SAMPLE as u64)
+		.read_to_end(&mut head)?;
+	let encoding = encoding.for_content(&head);
+	let mut reader = head.as_slice().chain(std::io::BufReader::new(decoder));
+	if is_binary(&head, encoding) {
+		let size = std::io::copy(&mut reader, &mut std::io::sink())?;
+		return Ok(hexdump_summary(&head, size));
+	}
+
+	let mut lines = VecDeque::new();
+	let mut line = Vec::new();
+	while max_lines > 0 && read_line(&mut reader, encoding.newline(), &mut line)? > 0 {
+		if lines.len() == max_lines {
+			lines.pop_front();
+		}
+		lines.push_back(decode(&line, encoding).into_owned());
+	}
+	Ok(lines.into())
+}
+
+/// Read all lines of a logfile, decompressing it if it ends with .gz
+///
+/// Binary content is summarised rather than split into lines (see encoding.rs).
+pub fn read_logfile_lines(path: &Path, encoding: Encoding) -> std::io::Result<Vec<String>> {
+	use std::io::Read;
+
+	let file = File::open(path)?;
+	let mut reader: Box<dyn Read> = match path.extension() {
+		Some(extension) if extension == "gz" => Box::new(MultiGzDecoder::new(file)),
+		_ => Box::new(file),
+	};
+	let mut bytes = Vec::new();
+	reader.read_to_end(&mut bytes)?;
+	Ok(decode_lines(&bytes, encoding))
+}
+
+fn boundary_marker(logfile: &str) -> String {
+	format!("==> {} <==", logfile)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+	Error,
+	Warning,
+	Info,
+	Debug,
+	Unknown,
+}
+
+impl Severity {
+	/// Guess the severity of a line from a level keyword such as ERROR or warn
+	///
+	/// Only the first few words are checked, as that is where logs put the
+	/// level, and later words are more likely to be part of the message.
+	/// Escape sequences are ignored, so a coloured level is still found.
+	pub fn of_line(line: &str) -> Severity {
+		let line = plain_text(line);
+		let words = line
+			.split(|c: char| !c.is_ascii_alphabetic())
+			.filter(|word| !word.is_empty())
+			.take(8);
+		for word in words {
+			let severity = match word.to_ascii_lowercase().as_str() {
+				"error" | "err" | "fatal" | "crit" | "critical" | "alert" | "emerg" | "panic" => {
+					Severity::Error
+				}
+				"warn" | "warning" => Severity::Warning,
+				"info" | "notice" => Severity::Info,
+				"debug" | "trace" => Severity::Debug,
+				_ => continue,
+			};
+			return severity;
+		}
+		Severity::Unknown
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum DashViewMain {
+	#[serde(rename = "horizontal")]
 	DashHorizontal,
+	#[serde(rename = "vertical")]
 	DashVertical,
 }
 
 pub struct DashState {
 	pub main_view: DashViewMain,
+	pub theme: Theme,
+	pub strip_ansi: bool,
+	pub time_window: Option<TimeWindow>, // As given on the command line
+	pub time_window_enabled: bool,
+	pub gutter: Gutter,
+	pub highlighter: Highlighter,
+	pub replay_status: Option<String>,
 	pub debug_window: bool,
 	pub debug_window_has_focus: bool,
+	pub last_render: Duration,
+	pub dirty: bool,                          // Changed since last drawn
+	pub last_drawn: Option<(Rect, Instant)>, // Size and time of the last draw
+	pub command_line: CommandLine,
+	pub file_picker: Option<FilePicker>, // While choosing a logfile to open
+	pub message: Option<Result<String, String>>, // Outcome of the last command, until a key is pressed
 	max_debug_window: usize,
 
 	// For --debug-window option
@@ -312,16 +1803,50 @@ impl DashState {
 	pub fn new() -> DashState {
 		DashState {
 			main_view: DashViewMain::DashHorizontal,
+			theme: Theme::named(DEFAULT_THEME).unwrap(),
+			strip_ansi: false,
+			time_window: None,
+			time_window_enabled: true,
+			gutter: Gutter::Off,
+			highlighter: Highlighter::default(),
+			replay_status: None,
 			dash_vertical: DashVertical::new(),
 			debug_window: false,
 			debug_window_has_focus: false,
+			last_render: Duration::default(),
+			dirty: true,
+			last_drawn: None,
+			command_line: CommandLine::default(),
+			file_picker: None,
+			message: None,
 			debug_window_list: StatefulList::new(),
 			max_debug_window: 100,
 		}
 	}
 
-	pub fn _debug_window(&mut self, text: &str) {
-		self.debug_window_list.items.push(text.to_string());
+	/// The time window applied to all monitors, unless switched off
+	pub fn active_time_window(&self) -> Option<TimeWindow> {
+		match self.time_window_enabled {
+			true => self.time_window,
+			false => None,
+		}
+	}
+
+	/// Record how long the dashboard took to draw, noting it if slow
+	pub fn record_render(&mut self, duration: Duration) {
+		self.last_render = duration;
+		if duration >= SLOW_RENDER {
+			self.diagnostic(&format!("slow render: {}ms", duration.as_millis()));
+		}
+	}
+
+	/// Add an internal event to the debug window
+	pub fn diagnostic(&mut self, text: &str) {
+		self.dirty |= self.debug_window;
+		let time = chrono::Local::now().format("%H:%M:%S");
+		self.debug_window_list
+			.items
+			.push(format!("{} {}", time, text));
 		let len = self.debug_window_list.items.len();
 
 		if len > self.max_debug_window {
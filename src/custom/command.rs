@@ -0,0 +1,412 @@
+//! Command line mode
+//!
+//! Edit src/custom/command.rs to create a customised fork of logtail-dash
+//!
+//! Press ':' to type a command in the status bar, Enter to run it or Esc to
+//! cancel. Up and Down recall earlier commands, and Tab completes command
+//! names, settings and file paths.
+//!
+//!   open LOGFILE                 follow another logfile in a new pane
+//!   close                        remove the pane with focus
+//!   filter [REGEX]               show only matching lines in the pane with focus
+//!   highlight [REGEX]            highlight matches in every pane, or toggle highlights
+//!   layout horizontal|vertical   arrange the panes
+//!   save FILE                    write the lines shown in the pane with focus to FILE
+//!   set SETTING VALUE            change lines_max, max_fps or tick_rate
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use regex::Regex;
+
+use super::app::{App, DashViewMain};
+use super::highlight::HighlightRule;
+
+/// Number of commands kept for recall with Up and Down
+const MAX_HISTORY: usize = 100;
+
+/// A command typed after ':'
+#[derive(Debug)]
+pub enum Command {
+	Open(String),
+	Close,
+	Filter(Option<Regex>),
+	Highlight(Option<Regex>),
+	Layout(DashViewMain),
+	Save(String),
+	Set(Setting, u64),
+}
+
+static COMMAND_NAMES: [&str; 7] = [
+	"open",
+	"close",
+	"filter",
+	"highlight",
+	"layout",
+	"save",
+	"set",
+];
+
+static LAYOUT_NAMES: [&str; 2] = ["horizontal", "vertical"];
+
+/// A setting which can be changed with the set command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Setting {
+	LinesMax,
+	MaxFps,
+	TickRate,
+}
+
+static SETTING_NAMES: [(Setting, &str); 3] = [
+	(Setting::LinesMax, "lines_max"),
+	(Setting::MaxFps, "max_fps"),
+	(Setting::TickRate, "tick_rate"),
+];
+
+impl std::fmt::Display for Setting {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match SETTING_NAMES.iter().find(|(setting, _)| setting == self) {
+			Some((_, name)) => write!(f, "{}", name),
+			None => write!(f, "{:?}", self),
+		}
+	}
+}
+
+impl FromStr for Setting {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match SETTING_NAMES.iter().find(|(_, name)| *name == s) {
+			Some((setting, _)) => Ok(*setting),
+			None => Err(format!("unknown setting: {}", s)),
+		}
+	}
+}
+
+impl FromStr for Command {
+	type Err = String;
+
+	/// Parse a command, whose argument is the rest of the line
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (name, arg) = split_word(s.trim());
+		let required = |what: &str| match arg {
+			"" => Err(format!("{}: {} needed", name, what)),
+			arg => Ok(arg.to_string()),
+		};
+		let regex = |arg: &str| match arg {
+			"" => Ok(None),
+			arg => Regex::new(arg)
+				.map(Some)
+				.map_err(|e| format!("{}: invalid pattern: {}", name, e)),
+		};
+
+		match name {
+			"open" => Ok(Command::Open(required("logfile")?)),
+			"close" => Ok(Command::Close),
+			"filter" => Ok(Command::Filter(regex(arg)?)),
+			"highlight" => Ok(Command::Highlight(regex(arg)?)),
+			"layout" => match arg {
+				"horizontal" => Ok(Command::Layout(DashViewMain::DashHorizontal)),
+				"vertical" => Ok(Command::Layout(DashViewMain::DashVertical)),
+				_ => Err(String::from("layout: horizontal or vertical needed")),
+			},
+			"save" => Ok(Command::Save(required("file")?)),
+			"set" => {
+				let (setting, value) = split_word(arg);
+				let setting = setting.parse::<Setting>()?;
+				match value.parse::<u64>() {
+					Ok(value) if value > 0 => Ok(Command::Set(setting, value)),
+					_ => Err(format!("set: invalid value: '{}'", value)),
+				}
+			}
+			_ => Err(format!("unknown command: {}", name)),
+		}
+	}
+}
+
+impl Command {
+	/// Act on the dashboard, returning a message describing what was done
+	pub fn run(self, app: &mut App) -> Result<String, String> {
+		match self {
+			Command::Open(path) => {
+				let path = expand_home(&path).to_string_lossy().to_string();
+				app.open_logfile(&path)?;
+				Ok(format!("opened {}", path))
+			}
+			Command::Close => {
+				let name = app
+					.close_logfile_with_focus()
+					.map_err(|e| format!("close: {}", e))?;
+				Ok(format!("closed {}", name))
+			}
+			Command::Filter(regex) => {
+				let monitor = app
+					.get_monitor_with_focus()
+					.ok_or_else(|| String::from("filter: no logfile has focus"))?;
+				let message = match &regex {
+					Some(regex) => format!("showing lines matching {}", regex),
+					None => String::from("showing all lines"),
+				};
+				monitor.set_line_filter(regex);
+				Ok(message)
+			}
+			Command::Highlight(None) => {
+				let highlighter = &mut app.dash_state.highlighter;
+				highlighter.enabled = !highlighter.enabled;
+				Ok(String::from(match highlighter.enabled {
+					true => "highlights on",
+					false => "highlights off",
+				}))
+			}
+			Command::Highlight(Some(regex)) => {
+				let message = format!("highlighting {}", regex);
+				let highlighter = &mut app.dash_state.highlighter;
+				highlighter.rules.push(HighlightRule {
+					regex,
+					style: app.dash_state.theme.highlight,
+					source: None,
+				});
+				highlighter.enabled = true;
+				Ok(message)
+			}
+			Command::Layout(layout) => {
+				app.dash_state.main_view = layout;
+				Ok(String::new())
+			}
+			Command::Save(path) => {
+				let monitor = app
+					.get_monitor_with_focus()
+					.ok_or_else(|| String::from("save: no logfile has focus"))?;
+				let mut text = String::new();
+				let mut lines = 0;
+				for i in monitor.shown_lines() {
+					for line in monitor.content.items[i].texts() {
+						text.push_str(&line);
+						text.push('\n');
+						lines += 1;
+					}
+				}
+				std::fs::write(expand_home(&path), text)
+					.map_err(|e| format!("save: unable to write {}: {}", path, e))?;
+				Ok(format!("saved {} lines to {}", lines, path))
+			}
+			Command::Set(setting, value) => {
+				match setting {
+					Setting::LinesMax => {
+						app.opt.lines_max = value as usize;
+						for monitor in app.monitors.values_mut() {
+							monitor.set_max_content(value as usize);
+						}
+					}
+					Setting::MaxFps => app.opt.max_fps = value.min(u32::MAX as u64) as u32,
+					Setting::TickRate => app.opt.tick_rate = value,
+				}
+				Ok(format!("{} set to {}", setting, value))
+			}
+		}
+	}
+}
+
+/// The command being typed, and those typed before
+#[derive(Default)]
+pub struct CommandLine {
+	pub active: bool,
+	pub input: String,
+	pub completions: Vec<String>, // Shown when Tab finds more than one
+	history: Vec<String>,         // Oldest first
+	recalled: Option<usize>,      // Position in history of the command shown
+}
+
+impl CommandLine {
+	pub fn start(&mut self) {
+		self.active = true;
+		self.input.clear();
+		self.completions.clear();
+		self.recalled = None;
+	}
+
+	/// Edit the command with a key, returning the command when Enter is pressed
+	pub fn handle_key(&mut self, key: &str) -> Option<String> {
+		self.completions.clear();
+		match key {
+			"Enter" => {
+				self.active = false;
+				let input = std::mem::take(&mut self.input);
+				if input.trim().is_empty() {
+					return None;
+				}
+				if self.history.last() != Some(&input) {
+					self.history.push(input.clone());
+				}
+				if self.history.len() > MAX_HISTORY {
+					self.history.remove(0);
+				}
+				return Some(input);
+			}
+			"Esc" => self.active = false,
+			"Backspace" => self.active = self.input.pop().is_some(), // Leave when empty
+			"Up" => {
+				let recalled = match self.recalled {
+					Some(recalled) => recalled.saturating_sub(1),
+					None => self.history.len().checked_sub(1)?,
+				};
+				self.recall(Some(recalled));
+			}
+			"Down" => match self.recalled {
+				Some(recalled) if recalled + 1 < self.history.len() => {
+					self.recall(Some(recalled + 1))
+				}
+				_ => self.recall(None),
+			},
+			"Tab" => self.complete(),
+			"Space" => self.input.push(' '),
+			key if key.chars().count() == 1 => self.input.push_str(key),
+			_ => (),
+		}
+		None
+	}
+
+	/// Show a command from the history, or an empty line for None
+	fn recall(&mut self, recalled: Option<usize>) {
+		self.recalled = recalled;
+		self.input = recalled
+			.and_then(|i| self.history.get(i).cloned())
+			.unwrap_or_default();
+	}
+
+	/// Complete the word being typed as far as it is the same for all candidates
+	fn complete(&mut self) {
+		let (start, candidates) = completions(&self.input);
+		let prefix = match candidates.as_slice() {
+			[] => return,
+			[only] => only.clone(),
+			_ => {
+				self.completions = candidates
+					.iter()
+					.map(|candidate| last_component(candidate.trim_end()).to_string())
+					.collect();
+				common_prefix(&candidates)
+			}
+		};
+		self.input.truncate(start);
+		self.input.push_str(&prefix);
+	}
+}
+
+/// The longest start shared by all the words
+fn common_prefix(words: &[String]) -> String {
+	let mut prefix = words.first().cloned().unwrap_or_default();
+	for word in words.iter().skip(1) {
+		let common = prefix
+			.char_indices()
+			.zip(word.chars())
+			.find(|((_, a), b)| a != b)
+			.map(|((i, _), _)| i);
+		// A word which is all of the prefix so far ends it there
+		prefix.truncate(common.unwrap_or(word.len()));
+	}
+	prefix
+}
+
+/// Where the word being typed starts, and the words which could complete it
+fn completions(input: &str) -> (usize, Vec<String>) {
+	let (name, arg) = split_word(input);
+	if arg.is_empty() && !input.ends_with(' ') {
+		let names = COMMAND_NAMES
+			.iter()
+			.filter(|command| command.starts_with(name))
+			.map(|command| format!("{} ", command))
+			.collect();
+		return (0, names);
+	}
+
+	let start = input.len() - arg.len();
+	let candidates = match name {
+		"open" | "save" => complete_path(arg),
+		"layout" => matching(&LAYOUT_NAMES, arg),
+		"set" if !arg.contains(' ') => {
+			let names: Vec<&str> = SETTING_NAMES.iter().map(|(_, name)| *name).collect();
+			matching(&names, arg)
+				.into_iter()
+				.map(|name| format!("{} ", name))
+				.collect()
+		}
+		_ => Vec::new(),
+	};
+	(start, candidates)
+}
+
+fn matching(words: &[&str], prefix: &str) -> Vec<String> {
+	words
+		.iter()
+		.filter(|word| word.starts_with(prefix))
+		.map(|word| word.to_string())
+		.collect()
+}
+
+/// Paths starting with the one typed, with a '/' after directories
+fn complete_path(typed: &str) -> Vec<String> {
+	let (dir, partial) = match typed.rfind('/') {
+		Some(i) => typed.split_at(i + 1),
+		None => ("", typed),
+	};
+	let entries = match std::fs::read_dir(match dir {
+		"" => PathBuf::from("."),
+		dir => expand_home(dir),
+	}) {
+		Ok(entries) => entries,
+		Err(_) => return Vec::new(),
+	};
+
+	let mut paths: Vec<String> = entries
+		.filter_map(|entry| entry.ok())
+		.filter_map(|entry| {
+			let name = entry.file_name().to_string_lossy().to_string();
+			if !name.starts_with(partial) || (name.starts_with('.') && !partial.starts_with('.')) {
+				return None;
+			}
+			let is_dir = entry.path().is_dir();
+			Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+		})
+		.collect();
+	paths.sort();
+	paths
+}
+
+/// The last part of a completed path, as shown when choosing between several
+fn last_component(path: &str) -> &str {
+	let trimmed = path.trim_end_matches('/');
+	match trimmed.rfind('/') {
+		Some(i) => &path[i + 1..],
+		None => path,
+	}
+}
+
+/// A path with a leading '~/' replaced by the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+	match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+		(Some(rest), Some(home)) => Path::new(&home).join(rest),
+		_ => PathBuf::from(path),
+	}
+}
+
+/// The first word, and the rest of the text with leading spaces removed
+fn split_word(text: &str) -> (&str, &str) {
+	match text.find(' ') {
+		Some(i) => (&text[..i], text[i..].trim_start()),
+		None => (text, ""),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn common_prefix_ends_at_shorter_words() {
+		let words =
+			|words: &[&str]| -> Vec<String> { words.iter().map(|word| word.to_string()).collect() };
+		let common_prefix = super::common_prefix;
+		assert_eq!(common_prefix(&words(&["app.log.1", "app.log"])), "app.log");
+		assert_eq!(common_prefix(&words(&["app.log", "app.log.1"])), "app.log");
+		assert_eq!(common_prefix(&words(&["save ", "set "])), "s");
+		assert_eq!(common_prefix(&words(&["café", "cafe"])), "caf");
+	}
+}
@@ -0,0 +1,88 @@
+//! Configuration file
+//!
+//! Edit src/custom/config.rs to create a customised fork of logtail-dash
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// Settings loaded from the configuration file (TOML)
+///
+/// For example:
+///
+///   [keys]
+///   "j" = "scroll-down"
+///   "Z Z" = "quit"
+///   "h" = "none"
+///
+///   [[highlight]]
+///   pattern = "timeout"
+///   style = "bold red"
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	/// Key sequences mapped to action names (see keymap.rs)
+	pub keys: BTreeMap<String, String>,
+
+	/// Name of a built-in or configured theme
+	pub theme: Option<String>,
+
+	/// Custom themes, each mapping theme elements to styles (see theme.rs)
+	pub themes: BTreeMap<String, BTreeMap<String, String>>,
+
+	/// Rules for highlighting parts of lines (see highlight.rs)
+	#[serde(rename = "highlight")]
+	pub highlights: Vec<HighlightConfig>,
+}
+
+/// A highlight rule: a regular expression and the style for its matches
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HighlightConfig {
+	pub pattern: String,
+	pub style: String,
+
+	/// Logfile or syslog pane the rule applies to, or all if not given
+	pub source: Option<String>,
+}
+
+impl Config {
+	/// Load the configuration from the given file, or from the default location
+	///
+	/// It is ok for the default configuration file not to exist.
+	pub fn load(path: Option<&str>) -> Result<Config, std::io::Error> {
+		let path = match path {
+			Some(path) => PathBuf::from(path),
+			None => match default_config_path() {
+				Some(path) if path.is_file() => path,
+				_ => return Ok(Config::default()),
+			},
+		};
+
+		let text = std::fs::read_to_string(&path)?;
+		toml::from_str(&text).map_err(|e| {
+			Error::new(
+				ErrorKind::InvalidData,
+				format!("{}: {}", path.to_string_lossy(), e),
+			)
+		})
+	}
+}
+
+/// The configuration file used if none is given on the command line
+pub fn default_config_path() -> Option<PathBuf> {
+	Some(config_dir()?.join("config.toml"))
+}
+
+/// Directory for logtail's configuration file and saved sessions
+pub fn config_dir() -> Option<PathBuf> {
+	let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+		Some(dir) => PathBuf::from(dir),
+		None => match std::env::var_os("HOME").or_else(|| std::env::var_os("APPDATA")) {
+			Some(dir) => PathBuf::from(dir).join(".config"),
+			None => return None,
+		},
+	};
+	Some(config_dir.join("logtail"))
+}
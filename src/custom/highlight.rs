@@ -0,0 +1,115 @@
+//! Highlight rules
+//!
+//! Edit src/custom/highlight.rs to create a customised fork of logtail-dash
+//!
+//! Each rule styles the parts of a line matching a regular expression, on
+//! top of any styling from the line's severity or ANSI escape sequences.
+//! Later rules are applied over earlier ones where matches overlap.
+use regex::Regex;
+use tui::style::Style;
+use tui::text::{Span, Spans};
+
+use super::config::Config;
+use super::theme::parse_style;
+
+pub struct HighlightRule {
+	pub regex: Regex,
+	pub style: Style,
+	pub source: Option<String>, // Only apply to this logfile or pane
+}
+
+/// The highlight rules from the configuration file
+pub struct Highlighter {
+	pub rules: Vec<HighlightRule>,
+	pub enabled: bool,
+}
+
+impl Default for Highlighter {
+	fn default() -> Self {
+		Highlighter {
+			rules: Vec::new(),
+			enabled: true,
+		}
+	}
+}
+
+impl Highlighter {
+	pub fn from_config(config: &Config) -> Result<Highlighter, String> {
+		let mut highlighter = Highlighter::default();
+		for rule in config.highlights.iter() {
+			let regex = Regex::new(&rule.pattern)
+				.map_err(|e| format!("invalid highlight pattern '{}': {}", rule.pattern, e))?;
+			highlighter.rules.push(HighlightRule {
+				regex,
+				style: parse_style(&rule.style)?,
+				source: rule.source.clone(),
+			});
+		}
+		Ok(highlighter)
+	}
+
+	/// Style the parts of a line from the given source which match a rule
+	pub fn apply(&self, source: &str, spans: Spans<'static>) -> Spans<'static> {
+		if !self.enabled || self.rules.is_empty() {
+			return spans;
+		}
+
+		let text: String = spans.0.iter().map(|span| span.content.as_ref()).collect();
+		let mut matches: Vec<(usize, usize, Style)> = Vec::new();
+		for rule in self.rules.iter() {
+			if rule
+				.source
+				.as_ref()
+				.is_some_and(|rule_source| rule_source != source)
+			{
+				continue;
+			}
+			for found in rule.regex.find_iter(&text) {
+				if !found.as_str().is_empty() {
+					matches.push((found.start(), found.end(), rule.style));
+				}
+			}
+		}
+		if matches.is_empty() {
+			return spans;
+		}
+
+		let mut boundaries: Vec<usize> = matches
+			.iter()
+			.flat_map(|(start, end, _)| vec![*start, *end])
+			.collect();
+		boundaries.sort_unstable();
+		boundaries.dedup();
+
+		// Split each span where a match starts or ends, and style the pieces
+		let mut highlighted = Vec::new();
+		let mut span_start = 0;
+		for span in spans.0.iter() {
+			let content = span.content.as_ref();
+			let span_end = span_start + content.len();
+			let mut cuts = vec![span_start];
+			cuts.extend(
+				boundaries
+					.iter()
+					.filter(|boundary| **boundary > span_start && **boundary < span_end),
+			);
+			cuts.push(span_end);
+
+			for piece in cuts.windows(2) {
+				let (start, end) = (piece[0], piece[1]);
+				let mut style = span.style;
+				for (match_start, match_end, match_style) in matches.iter() {
+					if *match_start <= start && end <= *match_end {
+						style = style.patch(*match_style);
+					}
+				}
+				highlighted.push(Span::styled(
+					content[start - span_start..end - span_start].to_string(),
+					style,
+				));
+			}
+			span_start = span_end;
+		}
+		Spans::from(highlighted)
+	}
+}
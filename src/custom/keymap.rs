@@ -0,0 +1,269 @@
+//! Key bindings
+//!
+//! Edit src/custom/keymap.rs to create a customised fork of logtail-dash
+//!
+//! Keys are named independently of the terminal backend:
+//! printable characters as themselves (e.g. "q", "G"), special keys by name
+//! (e.g. "Up", "PageDown", "Tab", "Space") and with modifiers as "C-f" (Ctrl)
+//! or "M-x" (Alt). A sequence of keys is written separated by spaces ("g g").
+use std::fmt;
+use std::str::FromStr;
+
+use super::config::Config;
+
+/// Something the user can ask the dashboard to do
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+	Quit,
+	FocusNext,
+	FocusPrevious,
+	ScrollUp,
+	ScrollDown,
+	ScrollPageUp,
+	ScrollPageDown,
+	ScrollTop,
+	ScrollBottom,
+	LayoutHorizontal,
+	LayoutVertical,
+	ToggleLayout,
+	ToggleExpand,
+	ToggleTemplates,
+	ToggleTimeWindow,
+	ToggleGutter,
+	ToggleHighlights,
+	ReplayPause,
+	ReplayStep,
+	ReplaySeekForward,
+	ReplaySeekBack,
+	ReplayFaster,
+	ReplaySlower,
+	ClearFilter,
+	CommandMode,
+	OpenFilePicker,
+	ClosePane,
+}
+
+static ACTION_NAMES: [(Action, &str); 27] = [
+	(Action::Quit, "quit"),
+	(Action::FocusNext, "focus-next"),
+	(Action::FocusPrevious, "focus-previous"),
+	(Action::ScrollUp, "scroll-up"),
+	(Action::ScrollDown, "scroll-down"),
+	(Action::ScrollPageUp, "scroll-page-up"),
+	(Action::ScrollPageDown, "scroll-page-down"),
+	(Action::ScrollTop, "scroll-top"),
+	(Action::ScrollBottom, "scroll-bottom"),
+	(Action::LayoutHorizontal, "layout-horizontal"),
+	(Action::LayoutVertical, "layout-vertical"),
+	(Action::ToggleLayout, "toggle-layout"),
+	(Action::ToggleExpand, "toggle-expand"),
+	(Action::ToggleTemplates, "toggle-templates"),
+	(Action::ToggleTimeWindow, "toggle-time-window"),
+	(Action::ToggleGutter, "toggle-gutter"),
+	(Action::ToggleHighlights, "toggle-highlights"),
+	(Action::ReplayPause, "replay-pause"),
+	(Action::ReplayStep, "replay-step"),
+	(Action::ReplaySeekForward, "replay-seek-forward"),
+	(Action::ReplaySeekBack, "replay-seek-back"),
+	(Action::ReplayFaster, "replay-faster"),
+	(Action::ReplaySlower, "replay-slower"),
+	(Action::ClearFilter, "clear-filter"),
+	(Action::CommandMode, "command-mode"),
+	(Action::OpenFilePicker, "open-file-picker"),
+	(Action::ClosePane, "close-pane"),
+];
+
+impl fmt::Display for Action {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match ACTION_NAMES.iter().find(|(action, _)| action == self) {
+			Some((_, name)) => write!(f, "{}", name),
+			None => write!(f, "{:?}", self),
+		}
+	}
+}
+
+impl FromStr for Action {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match ACTION_NAMES.iter().find(|(_, name)| *name == s) {
+			Some((action, _)) => Ok(*action),
+			None => Err(format!("unknown action: {}", s)),
+		}
+	}
+}
+
+static DEFAULT_BINDINGS: [(&str, Action); 41] = [
+	("q", Action::Quit),
+	("Q", Action::Quit),
+	("Tab", Action::FocusNext),
+	("Right", Action::FocusNext),
+	("BackTab", Action::FocusPrevious),
+	("Left", Action::FocusPrevious),
+	("Up", Action::ScrollUp),
+	("k", Action::ScrollUp),
+	("Down", Action::ScrollDown),
+	("j", Action::ScrollDown),
+	("PageUp", Action::ScrollPageUp),
+	("C-b", Action::ScrollPageUp),
+	("b", Action::ScrollPageUp),
+	("PageDown", Action::ScrollPageDown),
+	("C-f", Action::ScrollPageDown),
+	("Space", Action::ScrollPageDown),
+	("Home", Action::ScrollTop),
+	("g", Action::ScrollTop),
+	("End", Action::ScrollBottom),
+	("G", Action::ScrollBottom),
+	("h", Action::LayoutHorizontal),
+	("H", Action::LayoutHorizontal),
+	("v", Action::LayoutVertical),
+	("V", Action::LayoutVertical),
+	("l", Action::ToggleLayout),
+	("L", Action::ToggleLayout),
+	("Enter", Action::ToggleExpand),
+	("t", Action::ToggleTemplates),
+	("w", Action::ToggleTimeWindow),
+	("T", Action::ToggleGutter),
+	("i", Action::ToggleHighlights),
+	("p", Action::ReplayPause),
+	(".", Action::ReplayStep),
+	("]", Action::ReplaySeekForward),
+	("[", Action::ReplaySeekBack),
+	("+", Action::ReplayFaster),
+	("-", Action::ReplaySlower),
+	("Esc", Action::ClearFilter),
+	(":", Action::CommandMode),
+	("o", Action::OpenFilePicker),
+	("x", Action::ClosePane),
+];
+
+/// Maps key sequences to actions
+pub struct Keymap {
+	bindings: Vec<(Vec<String>, Action)>,
+	pending: Vec<String>,
+}
+
+impl Default for Keymap {
+	fn default() -> Self {
+		Keymap::new()
+	}
+}
+
+impl Keymap {
+	/// The default key bindings
+	pub fn new() -> Keymap {
+		let mut keymap = Keymap {
+			bindings: Vec::new(),
+			pending: Vec::new(),
+		};
+		for (keys, action) in DEFAULT_BINDINGS.iter() {
+			keymap.bind(keys, Some(*action));
+		}
+		keymap
+	}
+
+	/// The default key bindings modified by those in the configuration
+	pub fn from_config(config: &Config) -> Result<Keymap, String> {
+		let mut keymap = Keymap::new();
+		for (keys, action) in config.keys.iter() {
+			let action = match action.as_str() {
+				"none" => None,
+				action => Some(action.parse::<Action>()?),
+			};
+			for key in keys.split_whitespace() {
+				if !is_key_name(key) {
+					return Err(format!("invalid key '{}' in key binding '{}'", key, keys));
+				}
+			}
+			keymap.bind(keys, action);
+		}
+		Ok(keymap)
+	}
+
+	/// Bind a key sequence to an action, replacing any existing binding
+	///
+	/// An action of None removes the binding.
+	pub fn bind(&mut self, keys: &str, action: Option<Action>) {
+		let keys: Vec<String> = keys.split_whitespace().map(String::from).collect();
+		self.bindings.retain(|(bound_keys, _)| *bound_keys != keys);
+		if let Some(action) = action {
+			self.bindings.push((keys, action));
+		}
+	}
+
+	/// Handle a key press, returning an action if it completes a key sequence
+	///
+	/// A key which doesn't continue the pending sequence starts a new one.
+	/// Exact matches take priority, so a key bound on its own can't also
+	/// begin a longer sequence.
+	pub fn handle_key(&mut self, key: &str) -> Option<Action> {
+		self.pending.push(key.to_string());
+		loop {
+			let pending = &self.pending;
+			if let Some((_, action)) = self.bindings.iter().find(|(keys, _)| keys == pending) {
+				let action = *action;
+				self.pending.clear();
+				return Some(action);
+			}
+
+			let is_prefix = self
+				.bindings
+				.iter()
+				.any(|(keys, _)| keys.len() > pending.len() && keys.starts_with(pending));
+			if is_prefix {
+				return None;
+			}
+
+			if self.pending.len() > 1 {
+				self.pending.drain(..self.pending.len() - 1);
+			} else {
+				self.pending.clear();
+				return None;
+			}
+		}
+	}
+
+	/// Describe the active bindings, one line per action
+	pub fn describe(&self) -> Vec<String> {
+		let mut lines = Vec::new();
+		for (action, name) in ACTION_NAMES.iter() {
+			let keys: Vec<String> = self
+				.bindings
+				.iter()
+				.filter(|(_, bound_action)| bound_action == action)
+				.map(|(keys, _)| keys.join(" "))
+				.collect();
+			lines.push(format!("{:20} {}", name, keys.join(", ")));
+		}
+		lines
+	}
+}
+
+static SPECIAL_KEY_NAMES: [&str; 16] = [
+	"Up",
+	"Down",
+	"Left",
+	"Right",
+	"Tab",
+	"BackTab",
+	"Enter",
+	"Esc",
+	"Space",
+	"Backspace",
+	"Delete",
+	"Insert",
+	"Home",
+	"End",
+	"PageUp",
+	"PageDown",
+];
+
+fn is_key_name(key: &str) -> bool {
+	let key = key
+		.strip_prefix("C-")
+		.or_else(|| key.strip_prefix("M-"))
+		.unwrap_or(key);
+	key.chars().count() == 1
+		|| SPECIAL_KEY_NAMES.contains(&key)
+		|| (key.starts_with('F') && key[1..].parse::<u8>().is_ok())
+}
@@ -1,3 +1,11 @@
 pub mod app;
+pub mod command;
+pub mod config;
+pub mod highlight;
+pub mod keymap;
 pub mod opt;
+pub mod picker;
+pub mod replay;
+pub mod session;
+pub mod theme;
 pub mod ui;
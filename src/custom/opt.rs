@@ -4,8 +4,122 @@
 
 static MAX_CONTENT: &str = "100";
 
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
 pub use structopt::StructOpt;
 
+use crate::custom::replay::parse_replay_speed;
+use crate::custom::session::parse_session_name;
+use crate::shared::encoding::Encoding;
+use crate::shared::timestamp::{parse_duration, parse_time};
+
+/// How received syslog messages are divided between panes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyslogPanes {
+	Single,
+	Host,
+	App,
+}
+
+impl SyslogPanes {
+	pub fn variants() -> [&'static str; 3] {
+		["single", "host", "app"]
+	}
+}
+
+/// How consecutive repeated lines are collapsed into a single entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collapse {
+	None,
+	Exact,
+	Similar,
+}
+
+impl Collapse {
+	pub fn variants() -> [&'static str; 3] {
+		["none", "exact", "similar"]
+	}
+}
+
+impl std::str::FromStr for Collapse {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"none" => Ok(Collapse::None),
+			"exact" => Ok(Collapse::Exact),
+			"similar" => Ok(Collapse::Similar),
+			_ => Err(format!("unknown collapse mode: {}", s)),
+		}
+	}
+}
+
+/// Encoding of one logfile, or of all logfiles if no source is given
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceEncoding {
+	pub source: Option<String>,
+	pub encoding: Encoding,
+}
+
+impl std::str::FromStr for SourceEncoding {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.rsplit_once('=') {
+			Some((source, encoding)) => Ok(SourceEncoding {
+				source: Some(source.to_string()),
+				encoding: encoding.parse()?,
+			}),
+			None => Ok(SourceEncoding {
+				source: None,
+				encoding: s.parse()?,
+			}),
+		}
+	}
+}
+
+/// What is shown in the gutter before each line
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Gutter {
+	Off,
+	Absolute, // Time the line arrived
+	Relative, // How long ago the line arrived
+}
+
+impl Gutter {
+	pub fn variants() -> [&'static str; 3] {
+		["off", "absolute", "relative"]
+	}
+}
+
+impl std::str::FromStr for Gutter {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"off" => Ok(Gutter::Off),
+			"absolute" => Ok(Gutter::Absolute),
+			"relative" => Ok(Gutter::Relative),
+			_ => Err(format!("unknown gutter: {}", s)),
+		}
+	}
+}
+
+impl std::str::FromStr for SyslogPanes {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"single" => Ok(SyslogPanes::Single),
+			"host" => Ok(SyslogPanes::Host),
+			"app" => Ok(SyslogPanes::App),
+			_ => Err(format!("unknown syslog pane grouping: {}", s)),
+		}
+	}
+}
+
 #[derive(StructOpt, Debug)]
 #[structopt(
 	about = "Monitor multiple logfiles in the terminal.\nUse tab or arrow keys to navigate and scroll."
@@ -15,20 +129,118 @@ pub struct Opt {
 	#[structopt(short = "l", long, default_value = "100")]
 	pub lines_max: usize,
 
-	/// Time between ticks in milliseconds
+	/// Time between updates while replaying or scanning history, in milliseconds
 	#[structopt(short, long, default_value = "200")]
 	pub tick_rate: u64,
 
+	/// Maximum number of times a second the dashboard is redrawn
+	#[structopt(long, default_value = "30", value_name = "FPS")]
+	pub max_fps: u32,
+
+	/// Collapse consecutive repeated lines: 'none', 'exact' or 'similar' (differing only in numbers)
+	#[structopt(long, default_value = "exact", possible_values = &Collapse::variants())]
+	pub collapse: Collapse,
+
+	/// Replay the LOGFILEs, paced by their timestamps, instead of following them
+	#[structopt(long)]
+	pub replay: bool,
+
+	/// Speed of replay, as a multiple of the rate the lines were logged
+	#[structopt(long, default_value = "1", value_name = "MULTIPLE", parse(try_from_str = parse_replay_speed))]
+	pub replay_speed: f64,
+
+	/// Show when each line arrived in a gutter: 'off', 'absolute' or 'relative' (e.g. 3s ago)
+	#[structopt(long, default_value = "off", possible_values = &Gutter::variants())]
+	pub gutter: Gutter,
+
+	/// Only show lines from the last DURATION (e.g. 90s, 5m, 2h, 1d)
+	#[structopt(long, value_name = "DURATION", parse(try_from_str = parse_duration), conflicts_with_all = &["since", "until"])]
+	pub last: Option<Duration>,
+
+	/// Only show lines from TIME on (e.g. "2024-01-02 03:04:05", or "03:04" for today)
+	#[structopt(long, value_name = "TIME", parse(try_from_str = parse_time))]
+	pub since: Option<SystemTime>,
+
+	/// Only show lines up to TIME
+	#[structopt(long, value_name = "TIME", parse(try_from_str = parse_time))]
+	pub until: Option<SystemTime>,
+
 	/// Ignore any existing logfile content
 	#[structopt(short, long)]
 	pub ignore_existing: bool,
 
+	/// Read all existing content in the background, to count lines and templates
+	/// (only the last --lines-max lines are loaded otherwise)
+	#[structopt(long)]
+	pub full_scan: bool,
+
+	/// Also load history from rotated logfiles (e.g. LOGFILE.1, LOGFILE.2.gz)
+	#[structopt(short, long)]
+	pub rotated: bool,
+
+	/// Ignore rotated logfiles older than this many hours (0 for no limit)
+	#[structopt(long, default_value = "0", value_name = "HOURS")]
+	pub rotated_max_age: u64,
+
+	/// Configuration file [default: ~/.config/logtail/config.toml]
+	#[structopt(short, long, value_name = "FILE")]
+	pub config: Option<String>,
+
+	/// Colour theme: light, dark, high-contrast, mono or one from the configuration file
+	#[structopt(long, value_name = "NAME")]
+	pub theme: Option<String>,
+
+	/// Remove colours and other styling embedded in logfiles as ANSI escape sequences
+	#[structopt(long)]
+	pub strip_ansi: bool,
+
+	/// Print the active key bindings and exit
+	#[structopt(long)]
+	pub show_keys: bool,
+
 	/// One or more logfiles to monitor
 	#[structopt(name = "LOGFILE")]
 	pub files: Vec<String>,
 
-	// Show a debug window (not implemented in logtail)
-	/// (Not implemented in logtail).
-	#[structopt(short, long, hidden = true)]
+	/// Receive syslog messages over UDP on this address (e.g. 127.0.0.1:5514)
+	#[structopt(long, value_name = "ADDRESS")]
+	pub syslog_udp: Option<String>,
+
+	/// Receive syslog messages over TCP on this address (e.g. 127.0.0.1:5514)
+	#[structopt(long, value_name = "ADDRESS")]
+	pub syslog_tcp: Option<String>,
+
+	/// Show syslog messages in one pane, or one pane per 'host' or 'app'
+	#[structopt(long, default_value = "single", possible_values = &SyslogPanes::variants())]
+	pub syslog_panes: SyslogPanes,
+
+	/// Encoding of logfiles (utf-8, latin-1 or utf-16), or of one given as LOGFILE=ENCODING
+	#[structopt(long, value_name = "[LOGFILE=]ENCODING", number_of_values = 1)]
+	pub encoding: Vec<SourceEncoding>,
+
+	/// Restore the dashboard from session NAME, if saved before, and save it on exit
+	///
+	/// The layout, focus, toggles, and each pane's filters, scroll position
+	/// and pause are saved. There are no bookmarks or pane sizes to save.
+	#[structopt(long, value_name = "NAME", parse(try_from_str = parse_session_name))]
+	pub session: Option<String>,
+
+	/// Show a pane of internal events: files added, errors, rotations, dropped lines and slow renders
+	#[structopt(short, long)]
 	pub debug_window: bool,
 }
+
+impl Opt {
+	/// Encoding of a logfile, from the last --encoding which applies to it
+	pub fn encoding_for(&self, logfile: &str) -> Encoding {
+		let for_logfile = self
+			.encoding
+			.iter()
+			.rev()
+			.find(|e| e.source.as_deref() == Some(logfile));
+		let for_all = self.encoding.iter().rev().find(|e| e.source.is_none());
+		for_logfile
+			.or(for_all)
+			.map_or(Encoding::Utf8, |e| e.encoding)
+	}
+}
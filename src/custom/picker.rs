@@ -0,0 +1,131 @@
+//! File picker popup, for choosing a logfile to open
+//!
+//! Edit src/custom/picker.rs to create a customised fork of logtail-dash
+//!
+//! Shows the entries of a directory, directories first. Typing narrows the
+//! entries to those starting with what was typed, Enter opens a directory
+//! or chooses a file, and Backspace (with nothing typed) goes up to the
+//! parent directory.
+use std::path::{Path, PathBuf};
+
+use crate::shared::util::StatefulList;
+
+/// An entry in the directory shown
+pub struct PickerEntry {
+	pub name: String,
+	pub is_dir: bool,
+}
+
+pub struct FilePicker {
+	pub dir: PathBuf,
+	pub typed: String, // Only entries starting with this are shown
+	pub entries: StatefulList<PickerEntry>,
+	pub error: Option<String>, // If the directory can't be read
+}
+
+/// What the picker wants done after a key press
+#[derive(Debug, PartialEq)]
+pub enum PickerOutcome {
+	Continue,
+	Open(PathBuf),
+	Cancel,
+}
+
+impl FilePicker {
+	/// Start in the given directory
+	pub fn new(dir: &Path) -> FilePicker {
+		let mut picker = FilePicker {
+			dir: dir.to_path_buf(),
+			typed: String::new(),
+			entries: StatefulList::new(),
+			error: None,
+		};
+		picker.read_dir();
+		picker
+	}
+
+	/// Handle a key press, named as in the keymap
+	pub fn handle_key(&mut self, key: &str) -> PickerOutcome {
+		match key {
+			"Esc" => return PickerOutcome::Cancel,
+			"Enter" | "Right" => {
+				let entry = self
+					.entries
+					.state
+					.selected()
+					.and_then(|i| self.entries.items.get(i));
+				match entry {
+					Some(entry) if entry.is_dir => {
+						let dir = self.dir.join(&entry.name);
+						self.change_dir(&dir);
+					}
+					Some(entry) => return PickerOutcome::Open(self.dir.join(&entry.name)),
+					None => (),
+				}
+			}
+			"Backspace" | "Left" => {
+				if self.typed.pop().is_some() {
+					self.read_dir();
+				} else if let Some(parent) = self.dir.parent().map(Path::to_path_buf) {
+					self.change_dir(&parent);
+				}
+			}
+			"Up" => self.step(false),
+			"Down" => self.step(true),
+			"Space" => self.type_text(" "),
+			key if key.chars().count() == 1 => self.type_text(key),
+			_ => (),
+		}
+		PickerOutcome::Continue
+	}
+
+	fn type_text(&mut self, text: &str) {
+		self.typed.push_str(text);
+		self.read_dir();
+	}
+
+	fn change_dir(&mut self, dir: &Path) {
+		self.dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+		self.typed.clear();
+		self.read_dir();
+	}
+
+	/// Move the selection without wrapping
+	fn step(&mut self, down: bool) {
+		let len = self.entries.items.len();
+		if let Some(selected) = self.entries.state.selected() {
+			let selected = match down {
+				true => (selected + 1).min(len.saturating_sub(1)),
+				false => selected.saturating_sub(1),
+			};
+			self.entries.state.select(Some(selected));
+		}
+	}
+
+	/// List the entries of the directory which start with what was typed
+	fn read_dir(&mut self) {
+		self.entries.items.clear();
+		self.error = None;
+		match std::fs::read_dir(&self.dir) {
+			Ok(read_dir) => {
+				for entry in read_dir.filter_map(|entry| entry.ok()) {
+					let name = entry.file_name().to_string_lossy().to_string();
+					let hidden = name.starts_with('.') && !self.typed.starts_with('.');
+					if name.starts_with(&self.typed) && !hidden {
+						let is_dir = entry.path().is_dir();
+						self.entries.items.push(PickerEntry { name, is_dir });
+					}
+				}
+			}
+			Err(e) => self.error = Some(format!("{}: {}", self.dir.to_string_lossy(), e)),
+		}
+		self.entries
+			.items
+			.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+		let selected = match self.entries.items.is_empty() {
+			true => None,
+			false => Some(0),
+		};
+		self.entries.state.select(selected);
+	}
+}
@@ -0,0 +1,188 @@
+//! Replay of existing logfiles
+//!
+//! Edit src/custom/replay.rs to create a customised fork of logtail-dash
+//!
+//! Lines are released on each tick according to a replay clock, which runs
+//! from the earliest timestamp in the logfiles at a chosen speed. A line
+//! without a timestamp of its own is released with the line before it.
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{DateTime, Local};
+
+use super::app::read_logfile_lines;
+use super::opt::Opt;
+use crate::shared::timestamp::parse_line_timestamp;
+
+/// How far the replay clock moves when seeking forward or back
+pub const REPLAY_SEEK_STEP: Duration = Duration::from_secs(60);
+
+/// Slowest and fastest replay, as multiples of the rate the lines were logged
+const MIN_REPLAY_SPEED: f64 = 1.0 / 64.0;
+const MAX_REPLAY_SPEED: f64 = 65536.0;
+
+pub struct ReplayLine {
+	pub source: String,
+	pub text: String,
+	pub time: SystemTime,
+}
+
+pub struct Replay {
+	pub lines: Vec<ReplayLine>, // In time order, except where a file isn't
+	pub position: usize,        // Next line to release
+	pub clock: SystemTime,      // Time in the logfiles being replayed
+	pub speed: f64,
+	pub paused: bool,
+	start: SystemTime,
+	last_tick: Instant,
+}
+
+impl Replay {
+	/// Read the logfiles to be replayed
+	pub fn load(opt: &Opt) -> std::io::Result<Replay> {
+		let mut files = Vec::new();
+		for file in opt.files.iter() {
+			let mut time = None;
+			let mut lines = Vec::new();
+			for text in read_logfile_lines(Path::new(file), opt.encoding_for(file))? {
+				time = parse_line_timestamp(&text).or(time);
+				lines.push((file.to_string(), text, time));
+			}
+			files.push(lines);
+		}
+
+		let start = files
+			.iter()
+			.flatten()
+			.filter_map(|(_, _, time)| *time)
+			.min()
+			.unwrap_or_else(SystemTime::now);
+		let files: Vec<Vec<ReplayLine>> = files
+			.into_iter()
+			.map(|lines| {
+				lines
+					.into_iter()
+					.map(|(source, text, time)| ReplayLine {
+						source,
+						text,
+						time: time.unwrap_or(start),
+					})
+					.collect()
+			})
+			.collect();
+
+		let mut replay = Replay {
+			lines: merge_by_time(files),
+			position: 0,
+			clock: start,
+			speed: 1.0,
+			paused: false,
+			start,
+			last_tick: Instant::now(),
+		};
+		replay.set_speed(opt.replay_speed);
+		Ok(replay)
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.position >= self.lines.len()
+	}
+
+	/// Advance the clock by the time since the last tick, and release the lines now due
+	pub fn tick(&mut self) -> &[ReplayLine] {
+		let elapsed = self.last_tick.elapsed();
+		self.last_tick = Instant::now();
+		if !self.paused {
+			self.clock += Duration::from_secs_f64(elapsed.as_secs_f64() * self.speed);
+		}
+		self.release_due()
+	}
+
+	/// Release the next line, moving the clock to its time
+	pub fn step(&mut self) -> &[ReplayLine] {
+		if let Some(line) = self.lines.get(self.position) {
+			self.clock = self.clock.max(line.time);
+			let position = self.position;
+			self.position += 1;
+			&self.lines[position..self.position]
+		} else {
+			&[]
+		}
+	}
+
+	/// Move the clock forward, releasing the lines passed over
+	pub fn seek_forward(&mut self, step: Duration) -> &[ReplayLine] {
+		self.clock += step;
+		self.release_due()
+	}
+
+	/// Move the clock back, returning to the start so that the lines up to
+	/// the new time can be released again (after the content is cleared)
+	pub fn seek_back(&mut self, step: Duration) {
+		self.clock = self
+			.clock
+			.checked_sub(step)
+			.unwrap_or(self.start)
+			.max(self.start);
+		self.position = 0;
+	}
+
+	pub fn set_speed(&mut self, speed: f64) {
+		self.speed = speed.clamp(MIN_REPLAY_SPEED, MAX_REPLAY_SPEED);
+	}
+
+	/// Release the lines due by the replay clock
+	pub fn release_due(&mut self) -> &[ReplayLine] {
+		let first = self.position;
+		while let Some(line) = self.lines.get(self.position) {
+			if line.time > self.clock {
+				break;
+			}
+			self.position += 1;
+		}
+		&self.lines[first..self.position]
+	}
+
+	/// Describe the replay for the status bar
+	pub fn status(&self) -> String {
+		let state = match (self.is_finished(), self.paused) {
+			(true, _) => " finished",
+			(false, true) => " paused",
+			(false, false) => "",
+		};
+		format!(
+			"replay {} x{}{}",
+			DateTime::<Local>::from(self.clock).format("%Y-%m-%d %H:%M:%S"),
+			self.speed,
+			state
+		)
+	}
+}
+
+/// Merge the lines of each file into one sequence, taking whichever file's
+/// next line is earliest, so each file stays in order even where its
+/// timestamps go backwards
+fn merge_by_time(files: Vec<Vec<ReplayLine>>) -> Vec<ReplayLine> {
+	let mut merged = Vec::with_capacity(files.iter().map(Vec::len).sum());
+	let mut files: Vec<_> = files
+		.into_iter()
+		.map(|lines| lines.into_iter().peekable())
+		.collect();
+	while let Some((_, earliest)) = files
+		.iter_mut()
+		.enumerate()
+		.filter_map(|(i, lines)| Some((lines.peek()?.time, i)))
+		.min()
+	{
+		merged.extend(files[earliest].next());
+	}
+	merged
+}
+
+/// Parse --replay-speed, which must be a positive number
+pub fn parse_replay_speed(text: &str) -> Result<f64, String> {
+	match text.parse::<f64>() {
+		Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+		_ => Err(format!("invalid replay speed: {}", text)),
+	}
+}
@@ -0,0 +1,222 @@
+//! Sessions: dashboard state saved on exit and restored with --session
+//!
+//! A session is saved as TOML in the configuration directory, for example
+//! ~/.config/logtail/sessions/NAME.toml:
+//!
+//!   layout = "vertical"
+//!   focus = "/var/log/auth.log"
+//!   gutter = "relative"
+//!
+//!   [panes."/var/log/auth.log"]
+//!   template_filter = "user <*> logged in"
+//!   filter = "ERROR|WARN"
+//!   lines_from_end = 12
+//!   paused = true
+//!
+//! Panes are matched by logfile (or syslog pane) name, and template filters
+//! by the text of the template, so a session still applies after logfiles
+//! have grown or been rotated. Anything which no longer matches is ignored.
+//!
+//! logtail has no bookmarks and panes are always sized equally, so neither
+//! is part of a session.
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::custom::app::{App, DashViewMain, LogMonitor, MonitorView, DEBUG_WINDOW_NAME};
+use crate::custom::config::config_dir;
+use crate::custom::opt::Gutter;
+
+/// Dashboard state which is kept from one run of logtail to the next
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Session {
+	pub layout: DashViewMain,
+	pub focus: Option<String>,
+	pub gutter: Gutter,
+	pub time_window_enabled: bool,
+	pub highlights_enabled: bool,
+
+	// Tables must come after plain values when written as TOML
+	pub panes: BTreeMap<String, PaneSession>,
+}
+
+/// State of one pane
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaneSession {
+	/// Showing templates rather than lines
+	pub templates_view: bool,
+
+	/// Text of the template lines are filtered by
+	pub template_filter: Option<String>,
+
+	/// Regular expression lines are filtered by (see :filter)
+	pub filter: Option<String>,
+
+	/// Position of the selected line, if scrolled back from the newest
+	pub lines_from_end: Option<usize>,
+
+	/// Not following new lines, because scrolled back from the newest
+	pub paused: bool,
+}
+
+impl Default for Session {
+	fn default() -> Session {
+		Session {
+			layout: DashViewMain::DashHorizontal,
+			focus: None,
+			gutter: Gutter::Off,
+			time_window_enabled: true,
+			highlights_enabled: true,
+			panes: BTreeMap::new(),
+		}
+	}
+}
+
+impl Session {
+	/// The current state of the dashboard
+	pub fn from_app(app: &App) -> Session {
+		let panes = app
+			.monitors
+			.iter()
+			.map(|(name, monitor)| (name.clone(), PaneSession::from_monitor(monitor)))
+			.filter(|(_, pane)| *pane != PaneSession::default())
+			.collect();
+
+		Session {
+			layout: app.dash_state.main_view,
+			focus: match app.logfile_with_focus.is_empty() {
+				true => None,
+				false => Some(app.logfile_with_focus.clone()),
+			},
+			gutter: app.dash_state.gutter,
+			time_window_enabled: app.dash_state.time_window_enabled,
+			highlights_enabled: app.dash_state.highlighter.enabled,
+			panes,
+		}
+	}
+
+	/// Restore the dashboard to this state, as far as it still applies
+	pub fn apply(&self, app: &mut App) {
+		app.dash_state.main_view = self.layout;
+		app.dash_state.gutter = self.gutter;
+		app.dash_state.time_window_enabled = self.time_window_enabled;
+		app.dash_state.highlighter.enabled = self.highlights_enabled;
+
+		for (name, pane) in self.panes.iter() {
+			if let Some(monitor) = app.monitors.get_mut(name) {
+				pane.apply(monitor);
+			}
+		}
+		if let Some(focus) = &self.focus {
+			if app.monitors.contains_key(focus)
+				|| (app.opt.debug_window && focus == DEBUG_WINDOW_NAME)
+			{
+				app.set_logfile_focus(focus);
+			}
+		}
+	}
+
+	/// Load the named session, or a default session if it hasn't been saved
+	pub fn load(name: &str) -> Result<Session, Error> {
+		let path = session_path(name)?;
+		if !path.is_file() {
+			return Ok(Session::default());
+		}
+
+		let text = std::fs::read_to_string(&path)?;
+		toml::from_str(&text).map_err(|e| {
+			Error::new(
+				ErrorKind::InvalidData,
+				format!("{}: {}", path.to_string_lossy(), e),
+			)
+		})
+	}
+
+	pub fn save(&self, name: &str) -> Result<(), Error> {
+		let path = session_path(name)?;
+		let text = toml::to_string(self).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+		if let Some(dir) = path.parent() {
+			std::fs::create_dir_all(dir)?;
+		}
+		std::fs::write(&path, text)
+	}
+}
+
+impl PaneSession {
+	fn from_monitor(monitor: &LogMonitor) -> PaneSession {
+		let template_filter = monitor
+			.template_filter
+			.and_then(|id| monitor.templates.templates.get(id))
+			.map(|template| template.text());
+		let lines_from_end = match (
+			monitor.content.state.selected(),
+			monitor.content.items.len(),
+		) {
+			(Some(selected), len) if selected + 1 < len => Some(len - 1 - selected),
+			_ => None,
+		};
+
+		PaneSession {
+			templates_view: monitor.view == MonitorView::Templates,
+			template_filter,
+			filter: monitor
+				.line_filter
+				.as_ref()
+				.map(|regex| regex.as_str().to_string()),
+			lines_from_end,
+			paused: !monitor.is_following(),
+		}
+	}
+
+	fn apply(&self, monitor: &mut LogMonitor) {
+		if let Some(text) = &self.template_filter {
+			monitor.template_filter = monitor
+				.templates
+				.templates
+				.iter()
+				.position(|template| &template.text() == text);
+		}
+		if let Some(Ok(regex)) = self.filter.as_deref().map(Regex::new) {
+			monitor.set_line_filter(Some(regex));
+		}
+		let len = monitor.content.items.len();
+		match self.lines_from_end {
+			Some(from_end) if from_end < len => {
+				monitor.content.state.select(Some(len - 1 - from_end))
+			}
+			// Fewer lines than before, so stay paused on the oldest
+			_ if self.paused && len > 1 => monitor.content.state.select(Some(0)),
+			_ => (),
+		}
+		if self.templates_view && monitor.view != MonitorView::Templates {
+			monitor.toggle_templates_view();
+		}
+	}
+}
+
+/// File the named session is saved in
+pub fn session_path(name: &str) -> Result<PathBuf, Error> {
+	match config_dir() {
+		Some(dir) => Ok(dir.join("sessions").join(format!("{}.toml", name))),
+		None => Err(Error::new(
+			ErrorKind::NotFound,
+			"no directory for sessions (HOME is not set)",
+		)),
+	}
+}
+
+/// Check a session name given on the command line, which is used as a filename
+pub fn parse_session_name(name: &str) -> Result<String, String> {
+	let valid = !name.is_empty()
+		&& !name.starts_with('.')
+		&& !name.contains(|c: char| c == '/' || c == '\\' || c.is_control());
+	match valid {
+		true => Ok(name.to_string()),
+		false => Err(format!("invalid session name: {}", name)),
+	}
+}
@@ -0,0 +1,279 @@
+//! Colour themes
+//!
+//! Edit src/custom/theme.rs to create a customised fork of logtail-dash
+use std::collections::BTreeMap;
+
+use tui::style::{Color, Modifier, Style};
+
+use super::app::Severity;
+
+pub static DEFAULT_THEME: &str = "light";
+pub static THEME_NAMES: [&str; 4] = ["light", "dark", "high-contrast", "mono"];
+
+/// Styles used to draw the dashboard
+#[derive(Debug, Clone)]
+pub struct Theme {
+	pub border: Style,
+	pub border_focus: Style,
+	pub line: Style,
+	pub selection: Style,
+	pub selection_unfocused: Style,
+	pub error: Style,
+	pub warning: Style,
+	pub info: Style,
+	pub debug: Style,
+	pub highlight: Style,
+	pub gutter: Style,
+	pub activity: Style,
+	pub status_bar: Style,
+}
+
+impl Theme {
+	/// One of the built-in themes
+	pub fn named(name: &str) -> Option<Theme> {
+		let bold = Style::default().add_modifier(Modifier::BOLD);
+		let theme = match name {
+			"light" => Theme {
+				border: Style::default(),
+				border_focus: bold,
+				line: Style::default().fg(Color::Black).bg(Color::White),
+				selection: Style::default()
+					.bg(Color::LightGreen)
+					.add_modifier(Modifier::BOLD),
+				selection_unfocused: bold,
+				error: Style::default().fg(Color::Red),
+				warning: Style::default().fg(Color::Magenta),
+				info: Style::default(),
+				debug: Style::default().fg(Color::DarkGray),
+				highlight: Style::default()
+					.fg(Color::Blue)
+					.add_modifier(Modifier::BOLD),
+				gutter: Style::default().fg(Color::DarkGray),
+				activity: Style::default().fg(Color::Yellow),
+				status_bar: Style::default().fg(Color::White).bg(Color::DarkGray),
+			},
+			"dark" => Theme {
+				border: Style::default().fg(Color::DarkGray),
+				border_focus: Style::default().fg(Color::Cyan),
+				line: Style::default().fg(Color::Gray),
+				selection: Style::default()
+					.fg(Color::Black)
+					.bg(Color::Cyan)
+					.add_modifier(Modifier::BOLD),
+				selection_unfocused: Style::default().bg(Color::DarkGray),
+				error: Style::default().fg(Color::LightRed),
+				warning: Style::default().fg(Color::Yellow),
+				info: Style::default(),
+				debug: Style::default().fg(Color::DarkGray),
+				highlight: Style::default()
+					.fg(Color::LightCyan)
+					.add_modifier(Modifier::BOLD),
+				gutter: Style::default().fg(Color::DarkGray),
+				activity: Style::default().fg(Color::Green),
+				status_bar: Style::default().fg(Color::Black).bg(Color::Gray),
+			},
+			"high-contrast" => Theme {
+				border: Style::default().fg(Color::White),
+				border_focus: Style::default()
+					.fg(Color::Yellow)
+					.add_modifier(Modifier::BOLD),
+				line: Style::default().fg(Color::White).bg(Color::Black),
+				selection: Style::default()
+					.fg(Color::Black)
+					.bg(Color::Yellow)
+					.add_modifier(Modifier::BOLD),
+				selection_unfocused: Style::default().add_modifier(Modifier::REVERSED),
+				error: Style::default()
+					.fg(Color::LightRed)
+					.add_modifier(Modifier::BOLD),
+				warning: Style::default()
+					.fg(Color::LightYellow)
+					.add_modifier(Modifier::BOLD),
+				info: Style::default(),
+				debug: Style::default(),
+				highlight: Style::default()
+					.fg(Color::LightCyan)
+					.add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+				gutter: Style::default().fg(Color::Gray),
+				activity: Style::default().fg(Color::White),
+				status_bar: Style::default().fg(Color::Black).bg(Color::White),
+			},
+			"mono" => Theme {
+				border: Style::default(),
+				border_focus: bold,
+				line: Style::default(),
+				selection: Style::default().add_modifier(Modifier::REVERSED),
+				selection_unfocused: bold,
+				error: bold,
+				warning: bold,
+				info: Style::default(),
+				debug: Style::default().add_modifier(Modifier::DIM),
+				highlight: Style::default().add_modifier(Modifier::UNDERLINED),
+				gutter: Style::default().add_modifier(Modifier::DIM),
+				activity: Style::default(),
+				status_bar: Style::default().add_modifier(Modifier::REVERSED),
+			},
+			_ => return None,
+		};
+		Some(theme)
+	}
+
+	/// A theme from the configuration file, or failing that a built-in theme
+	///
+	/// A configured theme starts from the built-in theme named by its 'base'
+	/// (default "light") and replaces any of the styles it gives, for example:
+	///
+	///   [themes.mine]
+	///   base = "dark"
+	///   selection = "black on light-green bold"
+	pub fn from_config(
+		name: &str,
+		themes: &BTreeMap<String, BTreeMap<String, String>>,
+	) -> Result<Theme, String> {
+		let styles = match themes.get(name) {
+			Some(styles) => styles,
+			None => return Theme::named(name).ok_or(format!("unknown theme: {}", name)),
+		};
+
+		let base = styles
+			.get("base")
+			.map_or(DEFAULT_THEME, |base| base.as_str());
+		let mut theme = Theme::named(base).ok_or(format!("unknown base theme: {}", base))?;
+		for (element, spec) in styles.iter() {
+			if element == "base" {
+				continue;
+			}
+			let style = parse_style(spec)?;
+			match element.as_str() {
+				"border" => theme.border = style,
+				"border-focus" => theme.border_focus = style,
+				"line" => theme.line = style,
+				"selection" => theme.selection = style,
+				"selection-unfocused" => theme.selection_unfocused = style,
+				"error" => theme.error = style,
+				"warning" => theme.warning = style,
+				"info" => theme.info = style,
+				"debug" => theme.debug = style,
+				"highlight" => theme.highlight = style,
+				"gutter" => theme.gutter = style,
+				"activity" => theme.activity = style,
+				"status-bar" => theme.status_bar = style,
+				_ => return Err(format!("unknown theme element: {}", element)),
+			}
+		}
+		Ok(theme)
+	}
+
+	/// The same theme with colours removed, keeping only modifiers such as bold
+	///
+	/// Selection is shown in reverse video if it would otherwise be lost.
+	pub fn without_colour(&self) -> Theme {
+		let strip = |style: Style| Style::default().add_modifier(style.add_modifier);
+		let mut selection = strip(self.selection);
+		if selection == Style::default() || selection == strip(self.line) {
+			selection = selection.add_modifier(Modifier::REVERSED);
+		}
+		Theme {
+			border: strip(self.border),
+			border_focus: strip(self.border_focus),
+			line: strip(self.line),
+			selection,
+			selection_unfocused: strip(self.selection_unfocused),
+			error: strip(self.error),
+			warning: strip(self.warning),
+			info: strip(self.info),
+			debug: strip(self.debug),
+			highlight: strip(self.highlight),
+			gutter: strip(self.gutter),
+			activity: strip(self.activity),
+			status_bar: strip(self.status_bar).add_modifier(Modifier::REVERSED),
+		}
+	}
+
+	pub fn severity(&self, severity: Severity) -> Style {
+		match severity {
+			Severity::Error => self.error,
+			Severity::Warning => self.warning,
+			Severity::Info => self.info,
+			Severity::Debug => self.debug,
+			Severity::Unknown => Style::default(),
+		}
+	}
+}
+
+/// True if the NO_COLOR environment variable is set (see https://no-color.org)
+pub fn no_colour_requested() -> bool {
+	match std::env::var_os("NO_COLOR") {
+		Some(value) => !value.is_empty(),
+		None => false,
+	}
+}
+
+/// Parse a style such as "bold yellow on blue"
+///
+/// Words are colour names, modifiers, or "on" followed by a background colour.
+pub fn parse_style(spec: &str) -> Result<Style, String> {
+	let mut style = Style::default();
+	let mut words = spec.split_whitespace();
+	while let Some(word) = words.next() {
+		let modifier = match word {
+			"bold" => Some(Modifier::BOLD),
+			"dim" => Some(Modifier::DIM),
+			"italic" => Some(Modifier::ITALIC),
+			"underlined" => Some(Modifier::UNDERLINED),
+			"reversed" => Some(Modifier::REVERSED),
+			"crossed-out" => Some(Modifier::CROSSED_OUT),
+			_ => None,
+		};
+		if let Some(modifier) = modifier {
+			style = style.add_modifier(modifier);
+		} else if word == "on" {
+			let colour = words
+				.next()
+				.ok_or(format!("missing background colour in style: {}", spec))?;
+			style = style.bg(parse_colour(colour)?);
+		} else {
+			style = style.fg(parse_colour(word)?);
+		}
+	}
+	Ok(style)
+}
+
+/// Parse a colour name (e.g. "light-green"), "#rrggbb" or 256 colour index
+pub fn parse_colour(name: &str) -> Result<Color, String> {
+	let colour = match name {
+		"reset" | "default" => Color::Reset,
+		"black" => Color::Black,
+		"red" => Color::Red,
+		"green" => Color::Green,
+		"yellow" => Color::Yellow,
+		"blue" => Color::Blue,
+		"magenta" => Color::Magenta,
+		"cyan" => Color::Cyan,
+		"gray" | "grey" => Color::Gray,
+		"dark-gray" | "dark-grey" => Color::DarkGray,
+		"light-red" => Color::LightRed,
+		"light-green" => Color::LightGreen,
+		"light-yellow" => Color::LightYellow,
+		"light-blue" => Color::LightBlue,
+		"light-magenta" => Color::LightMagenta,
+		"light-cyan" => Color::LightCyan,
+		"white" => Color::White,
+		_ => {
+			if let Some(hex) = name.strip_prefix('#') {
+				let rgb = u32::from_str_radix(hex, 16)
+					.map_err(|_| format!("invalid colour: {}", name))?;
+				if hex.len() != 6 {
+					return Err(format!("invalid colour: {}", name));
+				}
+				Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+			} else {
+				let index = name
+					.parse::<u8>()
+					.map_err(|_| format!("invalid colour: {}", name))?;
+				Color::Indexed(index)
+			}
+		}
+	};
+	Ok(colour)
+}
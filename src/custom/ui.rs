@@ -1,66 +1,332 @@
 ///! Terminal based interface and dashboard
 ///!
 ///! Edit src/custom/ui.rs to create a customised fork of logtail-dash
-use super::app::{DashState, DashViewMain, LogMonitor, DEBUG_WINDOW_NAME};
-use std::collections::HashMap;
+use super::app::{
+	DashState, DashViewMain, LogLine, LogMonitor, MonitorView, Severity, TimeWindow,
+	DEBUG_WINDOW_NAME,
+};
+use super::opt::Gutter;
+use crate::shared::ansi::{ansi_to_spans, strip_ansi};
+use crate::shared::util::{format_age, format_size};
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, VecDeque};
+use std::time::SystemTime;
 
 use tui::{
 	backend::Backend,
-	layout::{Constraint, Corner, Direction, Layout, Rect},
+	buffer::Buffer,
+	layout::{Alignment, Constraint, Corner, Direction, Layout, Rect},
 	style::{Color, Modifier, Style},
+	symbols,
 	text::{Span, Spans, Text},
-	widgets::{Block, BorderType, Borders, List, ListItem, Widget},
+	widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Widget, Wrap},
 	Frame, Terminal,
 };
 
+/// Height of the debug window, when shown
+const DEBUG_WINDOW_HEIGHT: u16 = 10;
+
 pub fn draw_dashboard<B: Backend>(
 	f: &mut Frame<B>,
-	dash_state: &DashState,
+	dash_state: &mut DashState,
 	monitors: &mut HashMap<String, LogMonitor>,
 ) {
+	let debug_window_height = match dash_state.debug_window {
+		true => DEBUG_WINDOW_HEIGHT,
+		false => 0,
+	};
+	let chunks = Layout::default()
+		.direction(Direction::Vertical)
+		.constraints(
+			[
+				Constraint::Min(0),
+				Constraint::Length(debug_window_height),
+				Constraint::Length(1),
+			]
+			.as_ref(),
+		)
+		.split(f.size());
+
 	match dash_state.main_view {
-		DashViewMain::DashHorizontal => draw_dash_horizontal(f, dash_state, monitors),
-		DashViewMain::DashVertical => draw_dash_vertical(f, dash_state, monitors),
+		_ if monitors.is_empty() => draw_no_logfiles(f, chunks[0], dash_state),
+		DashViewMain::DashHorizontal => draw_dash_horizontal(f, chunks[0], dash_state, monitors),
+		DashViewMain::DashVertical => draw_dash_vertical(f, chunks[0], dash_state, monitors),
+	}
+	if dash_state.debug_window {
+		draw_debug_window(f, chunks[1], dash_state);
+	}
+	draw_status_bar(f, chunks[2], dash_state, monitors);
+	if dash_state.file_picker.is_some() {
+		draw_file_picker(f, chunks[0], dash_state);
+	}
+}
+
+/// In place of the panes when every logfile has been closed
+fn draw_no_logfiles<B: Backend>(f: &mut Frame<B>, area: Rect, dash_state: &DashState) {
+	let text = Paragraph::new(Spans::from(
+		"No logfiles are open. Open one with the file picker or :open LOGFILE",
+	))
+	.style(dash_state.theme.line)
+	.wrap(Wrap { trim: true })
+	.block(
+		Block::default()
+			.borders(Borders::ALL)
+			.border_style(dash_state.theme.border),
+	);
+	f.render_widget(text, area);
+}
+
+/// Popup listing a directory, to choose a logfile to open
+fn draw_file_picker<B: Backend>(f: &mut Frame<B>, area: Rect, dash_state: &mut DashState) {
+	let theme = &dash_state.theme;
+	let picker = match dash_state.file_picker.as_mut() {
+		Some(picker) => picker,
+		None => return,
+	};
+
+	let width = (area.width * 3 / 4).max(area.width.min(40));
+	let height = (area.height * 3 / 4).max(area.height.min(10));
+	let popup = Rect::new(
+		area.x + (area.width - width) / 2,
+		area.y + (area.height - height) / 2,
+		width,
+		height,
+	);
+
+	let items: Vec<ListItem> = match &picker.error {
+		Some(error) => vec![ListItem::new(Spans::from(error.clone())).style(theme.line.patch(theme.error))],
+		None => picker
+			.entries
+			.items
+			.iter()
+			.map(|entry| {
+				let name = match entry.is_dir {
+					true => format!("{}/", entry.name),
+					false => entry.name.clone(),
+				};
+				ListItem::new(Spans::from(name)).style(theme.line)
+			})
+			.collect(),
+	};
+	let mut dir = picker.dir.to_string_lossy().to_string();
+	if !dir.ends_with('/') {
+		dir.push('/');
 	}
+	let list = List::new(items)
+		.block(
+			Block::default()
+				.borders(Borders::ALL)
+				.border_style(theme.border_focus)
+				.title(format!("Open logfile: {}{}", dir, picker.typed)),
+		)
+		.highlight_style(theme.selection);
+
+	f.render_widget(Clear, popup);
+	f.render_stateful_widget(list, popup, &mut picker.entries.state);
 }
 
 fn draw_dash_horizontal<B: Backend>(
 	f: &mut Frame<B>,
+	area: Rect,
 	dash_state: &DashState,
 	monitors: &mut HashMap<String, LogMonitor>,
 ) {
 	let constraints = make_percentage_constraints(monitors.len());
 
-	let size = f.size();
 	let chunks = Layout::default()
 		.direction(Direction::Vertical)
-		.constraints(constraints.as_ref())
-		.split(size);
+		.constraints(constraints.as_slice())
+		.split(area);
 
-	let mut chunk = 0;
-	for (logfile, mut monitor) in monitors.iter_mut() {
-		draw_logfile(f, chunks[chunk], &logfile, &mut monitor);
-		chunk += 1;
+	for (chunk, (logfile, monitor)) in monitors_in_order(monitors).into_iter().enumerate() {
+		draw_logfile(f, chunks[chunk], logfile, monitor, dash_state);
 	}
 }
 
+/// Monitors in the order they were created, so panes keep their places
+fn monitors_in_order(
+	monitors: &mut HashMap<String, LogMonitor>,
+) -> Vec<(&String, &mut LogMonitor)> {
+	let mut ordered: Vec<(&String, &mut LogMonitor)> = monitors.iter_mut().collect();
+	ordered.sort_by_key(|(_, monitor)| monitor.index);
+	ordered
+}
+
 fn draw_dash_vertical<B: Backend>(
 	f: &mut Frame<B>,
+	area: Rect,
 	dash_state: &DashState,
 	monitors: &mut HashMap<String, LogMonitor>,
 ) {
 	let constraints = make_percentage_constraints(monitors.len());
-	let size = f.size();
 	let chunks = Layout::default()
 		.direction(Direction::Horizontal)
-		.constraints(constraints.as_ref())
-		.split(size);
+		.constraints(constraints.as_slice())
+		.split(area);
 
-	let mut chunk = 0;
-	for (logfile, mut monitor) in monitors.iter_mut() {
-		draw_logfile(f, chunks[chunk], &logfile, &mut monitor);
-		chunk += 1;
+	for (chunk, (logfile, monitor)) in monitors_in_order(monitors).into_iter().enumerate() {
+		draw_logfile(f, chunks[chunk], logfile, monitor, dash_state);
+	}
+}
+
+/// Status of the monitor with focus on the left, and of the whole dashboard on the right
+///
+/// While a command is typed, the status bar shows it instead.
+fn draw_status_bar<B: Backend>(
+	f: &mut Frame<B>,
+	area: Rect,
+	dash_state: &DashState,
+	monitors: &HashMap<String, LogMonitor>,
+) {
+	let style = dash_state.theme.status_bar;
+	if dash_state.command_line.active {
+		draw_command_line(f, area, dash_state);
+		return;
+	}
+	if let Some(message) = &dash_state.message {
+		let (text, style) = match message {
+			Ok(text) => (text, style),
+			Err(text) => (text, style.patch(dash_state.theme.error)),
+		};
+		f.render_widget(Paragraph::new(Spans::from(format!(" {}", text))).style(style), area);
+		return;
+	}
+
+	let mut focus_status = Vec::new();
+	if let Some(monitor) = monitors.values().find(|monitor| monitor.has_focus) {
+		focus_status.push(monitor.logfile.clone());
+		if let Ok(metadata) = std::fs::metadata(&monitor.logfile) {
+			let mut file_status = format_size(metadata.len());
+			if let Ok(modified) = metadata.modified() {
+				if let Ok(age) = SystemTime::now().duration_since(modified) {
+					file_status = format!("{}, modified {} ago", file_status, format_age(age));
+				}
+			}
+			focus_status.push(file_status);
+		}
+		focus_status.push(format!(
+			"{:.1} lines/s",
+			monitor.activity.lines_per_second()
+		));
+		focus_status.push(format!(
+			"{}/{} lines kept of {} seen",
+			monitor.content.items.len(),
+			monitor.max_content(),
+			monitor.lines_seen
+		));
+		focus_status.push(String::from(match monitor.is_following() {
+			true => "following",
+			false => "paused",
+		}));
+		if let Some(template) = monitor.template_filter {
+			if let Some(template) = monitor.templates.templates.get(template) {
+				focus_status.push(format!("template: {}", template.text()));
+			}
+		}
+		if let Some(regex) = &monitor.line_filter {
+			focus_status.push(format!("filter: {}", regex));
+		}
+	} else if dash_state.debug_window_has_focus {
+		focus_status.push(String::from(DEBUG_WINDOW_NAME));
+	}
+
+	if let Some(replay_status) = &dash_state.replay_status {
+		focus_status.push(replay_status.clone());
+	}
+	if !dash_state.highlighter.enabled {
+		focus_status.push(String::from("highlights off"));
+	}
+	if let Some(time_window) = dash_state.active_time_window() {
+		focus_status.push(format_time_window(&time_window));
 	}
+
+	let buffer_bytes: usize = monitors
+		.values()
+		.map(|monitor| monitor.content_bytes())
+		.sum();
+	// Padded so the layout doesn't shift as the buffers grow
+	let dash_status = format!(
+		"| {} sources, buffers {:>9} ",
+		monitors.len(),
+		format_size(buffer_bytes as u64)
+	);
+
+	let chunks = Layout::default()
+		.direction(Direction::Horizontal)
+		.constraints(
+			[
+				Constraint::Min(0),
+				Constraint::Length(dash_status.len() as u16),
+			]
+			.as_ref(),
+		)
+		.split(area);
+	// Leaving a space before the separator that starts the dash status
+	let mut focus_text = fit_fields(&focus_status, chunks[0].width.saturating_sub(1));
+	focus_text.push(' ');
+	let focus_widget = Paragraph::new(Spans::from(focus_text)).style(style);
+	f.render_widget(focus_widget, chunks[0]);
+	let dash_widget = Paragraph::new(Spans::from(dash_status))
+		.style(style)
+		.alignment(Alignment::Right);
+	f.render_widget(dash_widget, chunks[1]);
+}
+
+/// Join as many of the fields as fit in a width, marking any dropped
+/// from the end with "…", and cutting the first field if even that is
+/// too wide
+fn fit_fields(fields: &[String], width: u16) -> String {
+	for kept in (1..=fields.len()).rev() {
+		let mut text = format!(" {}", fields[..kept].join(" | "));
+		if kept < fields.len() {
+			text.push_str(" | …");
+		}
+		if text.chars().count() <= width as usize {
+			return text;
+		}
+	}
+	ellipsize(&format!(" {}", fields.join(" | ")), width)
+}
+
+/// Cut text to fit a width, ending with "…" if anything was cut
+fn ellipsize(text: &str, width: u16) -> String {
+	let width = width as usize;
+	if text.chars().count() <= width {
+		return String::from(text);
+	}
+	let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+	if width > 0 {
+		cut.push('…');
+	}
+	cut
+}
+
+/// The command being typed, and any completions to choose between
+fn draw_command_line<B: Backend>(f: &mut Frame<B>, area: Rect, dash_state: &DashState) {
+	let command_line = &dash_state.command_line;
+	let prompt = format!(":{}", command_line.input);
+	let completions = command_line.completions.join("  ");
+	let style = dash_state.theme.status_bar;
+
+	let chunks = Layout::default()
+		.direction(Direction::Horizontal)
+		.constraints(
+			[
+				Constraint::Min(0),
+				Constraint::Length(completions.chars().count() as u16 + 1),
+			]
+			.as_ref(),
+		)
+		.split(area);
+	f.render_widget(Paragraph::new(Spans::from(prompt.clone())).style(style), chunks[0]);
+	f.render_widget(
+		Paragraph::new(Spans::from(completions))
+			.style(style)
+			.alignment(Alignment::Right),
+		chunks[1],
+	);
+	let cursor = (prompt.chars().count() as u16).min(chunks[0].width.saturating_sub(1));
+	f.set_cursor(chunks[0].x + cursor, chunks[0].y);
 }
 
 fn draw_logfile<B: Backend>(
@@ -68,65 +334,305 @@ fn draw_logfile<B: Backend>(
 	area: Rect,
 	logfile: &String,
 	monitor: &mut LogMonitor,
+	dash_state: &DashState,
 ) {
-	let highlight_style = match monitor.has_focus {
-		true => Style::default()
-			.bg(Color::LightGreen)
-			.add_modifier(Modifier::BOLD),
-		false => Style::default().add_modifier(Modifier::BOLD),
+	let theme = &dash_state.theme;
+	let (highlight_style, border_style) = match monitor.has_focus {
+		true => (theme.selection, theme.border_focus),
+		false => (theme.selection_unfocused, theme.border),
 	};
 
-	let items: Vec<ListItem> = monitor
-		.content
-		.items
+	if monitor.view == MonitorView::Templates {
+		draw_templates(f, area, logfile, monitor, dash_state);
+		return;
+	}
+
+	let height = area.height.saturating_sub(2) as usize;
+	let (in_view, selected) = lines_in_view(monitor, height);
+	let items: Vec<ListItem> = in_view
 		.iter()
-		.map(|s| {
-			ListItem::new(vec![Spans::from(s.clone())])
-				.style(Style::default().fg(Color::Black).bg(Color::White))
-		})
+		.map(|i| log_line_item(&monitor.content.items[*i], logfile, dash_state, height))
 		.collect();
+	let mut view_state = ListState::default();
+	view_state.select(selected);
 
-	let vault_log_title = format!("Vault Log ({})", logfile);
+	let vault_log_title = match monitor.scan_percent_done() {
+		Some(percent) => format!("Vault Log ({}) scanning history {}%", logfile, percent),
+		None => format!("Vault Log ({})", logfile),
+	};
 
 	let logfile_widget = List::new(items)
 		.block(
 			Block::default()
 				.borders(Borders::ALL)
+				.border_style(border_style)
 				.title(vault_log_title.clone()),
 		)
 		.highlight_style(highlight_style);
 
-	f.render_stateful_widget(logfile_widget, area, &mut monitor.content.state);
+	f.render_stateful_widget(logfile_widget, area, &mut view_state);
+	monitor.visible_lines = area.height.saturating_sub(2) as usize;
+
+	// Activity strip along the bottom border
+	if area.height > 2 && area.width > 2 {
+		monitor.activity.roll_forward();
+		let strip_area = Rect::new(area.x + 1, area.y + area.height - 1, area.width - 2, 1);
+		let activity_strip = ActivityStrip {
+			counts: &monitor.activity.counts,
+			cursor: monitor.selected_seconds_ago(),
+			style: theme.activity,
+		};
+		f.render_widget(activity_strip, strip_area);
+	}
 }
 
-fn draw_debug_window<B: Backend>(
+/// The templates mined from a logfile, in order of first appearance
+fn draw_templates<B: Backend>(
 	f: &mut Frame<B>,
 	area: Rect,
-	dash_state: &mut DashState,
+	logfile: &String,
 	monitor: &mut LogMonitor,
+	dash_state: &DashState,
 ) {
-	let highlight_style = match dash_state.debug_window_has_focus {
-		true => Style::default()
-			.bg(Color::LightGreen)
-			.add_modifier(Modifier::BOLD),
-		false => Style::default().add_modifier(Modifier::BOLD),
+	let theme = &dash_state.theme;
+	let (highlight_style, border_style) = match monitor.has_focus {
+		true => (theme.selection, theme.border_focus),
+		false => (theme.selection_unfocused, theme.border),
+	};
+
+	let items: Vec<ListItem> = monitor
+		.templates
+		.templates
+		.iter()
+		.map(|template| {
+			let style = match monitor.template_filter == Some(template.id) {
+				true => theme.line.patch(theme.highlight),
+				false => theme.line,
+			};
+			ListItem::new(Spans::from(format!(
+				"{:>7} {} {} {}",
+				template.count,
+				format_time(template.first_seen),
+				format_time(template.last_seen),
+				template.text()
+			)))
+			.style(style)
+		})
+		.collect();
+
+	let templates_title = format!("Templates ({}) - count, first, last seen", logfile);
+
+	let templates_widget = List::new(items)
+		.block(
+			Block::default()
+				.borders(Borders::ALL)
+				.border_style(border_style)
+				.title(templates_title),
+		)
+		.highlight_style(highlight_style);
+
+	f.render_stateful_widget(templates_widget, area, &mut monitor.templates_state);
+	monitor.visible_lines = area.height.saturating_sub(2) as usize;
+}
+
+/// Indices of the content lines in view, and the position of the selection among them
+///
+/// Only the lines which fit in the pane are made into ListItems. Like List,
+/// the view moves only as far as needed to keep the selection in it.
+fn lines_in_view(monitor: &mut LogMonitor, height: usize) -> (Vec<usize>, Option<usize>) {
+	let shown = match monitor.is_filtered() {
+		true => Some(monitor.shown_lines()),
+		false => None,
+	};
+	let (count, position) = match &shown {
+		Some(shown) => (shown.len(), monitor.shown_position(shown)),
+		None => {
+			let count = monitor.content.items.len();
+			let last = count.checked_sub(1);
+			let selected = monitor.content.state.selected().or(last);
+			(count, selected.zip(last).map(|(selected, last)| selected.min(last)))
+		}
+	};
+
+	let height = height.max(1);
+	let offset = match position {
+		Some(position) if position < monitor.view_offset => position,
+		Some(position) if position >= monitor.view_offset + height => position + 1 - height,
+		_ => monitor.view_offset.min(count.saturating_sub(height)),
+	};
+	monitor.view_offset = offset;
+
+	let in_view = (offset..count.min(offset + height))
+		.map(|i| shown.as_ref().map_or(i, |shown| shown[i]))
+		.collect();
+	(in_view, position.map(|position| position - offset))
+}
+
+/// A line of content, with a count of any repeats and, if expanded, the repeats themselves
+///
+/// An expanded line shows only as many of the latest repeats as fit in
+/// max_rows, as the list can't draw an item taller than its area.
+fn log_line_item(
+	line: &LogLine,
+	source: &str,
+	dash_state: &DashState,
+	max_rows: usize,
+) -> ListItem<'static> {
+	let theme = &dash_state.theme;
+	let style = theme
+		.line
+		.patch(theme.severity(Severity::of_line(&line.text)));
+	let to_spans = |text: &str| {
+		let spans = match dash_state.strip_ansi {
+			true => Spans::from(Span::styled(strip_ansi(text), style)),
+			false => ansi_to_spans(text, style),
+		};
+		dash_state.highlighter.apply(source, spans)
+	};
+
+	let mut spans = to_spans(&line.text);
+	if dash_state.gutter != Gutter::Off {
+		let gutter = gutter_text(line.arrived, dash_state.gutter);
+		spans.0.insert(0, Span::styled(gutter, theme.gutter));
+	}
+	if let Some(last_repeat) = line.last_repeat() {
+		spans.0.push(Span::styled(
+			format!(" (×{}, last {})", line.count(), format_time(last_repeat)),
+			style.patch(theme.highlight),
+		));
+	}
+
+	let mut lines = vec![spans];
+	if let Some(repeats) = line.repeats.as_deref().filter(|repeats| repeats.expanded) {
+		let mut shown = repeats.kept.len().min(max_rows.saturating_sub(1));
+		if shown < repeats.count {
+			shown = shown.min(max_rows.saturating_sub(2)); // Leaving a row for the rest
+		}
+		let earlier = repeats.count - shown;
+		if earlier > 0 {
+			let reason = match shown == repeats.kept.len() {
+				true => "kept",
+				false => "shown",
+			};
+			lines.push(Spans::from(Span::styled(
+				format!("  … {} earlier repeats not {}", earlier, reason),
+				style.patch(theme.highlight),
+			)));
+		}
+		for repeat in repeats.kept[repeats.kept.len() - shown..].iter() {
+			// Exact repeats aren't stored, as they are the same as the line
+			let mut spans = to_spans(repeat.text.as_deref().unwrap_or(&line.text));
+			let time = repeat.time.map_or(String::from("--:--:--"), format_time);
+			spans
+				.0
+				.insert(0, Span::styled(format!("  ↳ {} ", time), style));
+			lines.push(spans);
+		}
+	}
+	ListItem::new(lines).style(style)
+}
+
+/// When a line arrived, as a time or how long ago, padded to a fixed width
+fn gutter_text(arrived: Option<SystemTime>, gutter: Gutter) -> String {
+	let text = match (arrived, gutter) {
+		(Some(arrived), Gutter::Relative) => {
+			let age = SystemTime::now()
+				.duration_since(arrived)
+				.unwrap_or_default();
+			format!("{} ago", format_age(age))
+		}
+		(Some(arrived), _) => format_time(arrived),
+		(None, _) => String::from("?"),
+	};
+	format!("{:>8} ", text)
+}
+
+fn format_time_window(time_window: &TimeWindow) -> String {
+	let format = |time: &SystemTime| {
+		DateTime::<Local>::from(*time)
+			.format("%Y-%m-%d %H:%M:%S")
+			.to_string()
+	};
+	match time_window {
+		TimeWindow::Last(duration) => format!("last {}", format_age(*duration)),
+		TimeWindow::Range(Some(from), Some(to)) => {
+			format!("from {} to {}", format(from), format(to))
+		}
+		TimeWindow::Range(Some(from), None) => format!("since {}", format(from)),
+		TimeWindow::Range(None, Some(to)) => format!("until {}", format(to)),
+		TimeWindow::Range(None, None) => String::from("all time"),
+	}
+}
+
+fn format_time(time: SystemTime) -> String {
+	DateTime::<Local>::from(time).format("%H:%M:%S").to_string()
+}
+
+/// A sparkline of lines per second, newest on the right
+///
+/// Seconds with no activity are left blank so that the strip can be
+/// drawn over a border. The cursor highlights a given number of seconds ago.
+struct ActivityStrip<'a> {
+	counts: &'a VecDeque<u64>,
+	cursor: Option<usize>,
+	style: Style,
+}
+
+impl<'a> Widget for ActivityStrip<'a> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let bars = [
+			symbols::bar::ONE_EIGHTH,
+			symbols::bar::ONE_QUARTER,
+			symbols::bar::THREE_EIGHTHS,
+			symbols::bar::HALF,
+			symbols::bar::FIVE_EIGHTHS,
+			symbols::bar::THREE_QUARTERS,
+			symbols::bar::SEVEN_EIGHTHS,
+			symbols::bar::FULL,
+		];
+
+		let width = (area.width as usize).min(self.counts.len());
+		let visible = self.counts.iter().skip(self.counts.len() - width);
+		let max = visible.clone().max().copied().unwrap_or(0);
+
+		for (i, count) in visible.enumerate() {
+			let seconds_ago = width - 1 - i;
+			let cell = buf.get_mut(area.right() - 1 - seconds_ago as u16, area.y);
+			if *count > 0 {
+				let level = (count * bars.len() as u64).div_ceil(max) as usize;
+				cell.set_symbol(bars[level - 1]).set_style(self.style);
+			}
+			if self.cursor == Some(seconds_ago) {
+				cell.set_style(self.style.add_modifier(Modifier::REVERSED));
+			}
+		}
+	}
+}
+
+fn draw_debug_window<B: Backend>(f: &mut Frame<B>, area: Rect, dash_state: &mut DashState) {
+	let theme = &dash_state.theme;
+	let (highlight_style, border_style) = match dash_state.debug_window_has_focus {
+		true => (theme.selection, theme.border_focus),
+		false => (theme.selection_unfocused, theme.border),
 	};
 
 	let items: Vec<ListItem> = dash_state
 		.debug_window_list
 		.items
 		.iter()
-		.map(|s| {
-			ListItem::new(vec![Spans::from(s.clone())])
-				.style(Style::default().fg(Color::Black).bg(Color::White))
-		})
+		.map(|s| ListItem::new(vec![Spans::from(s.clone())]).style(theme.line))
 		.collect();
 
 	let debug_window_widget = List::new(items)
 		.block(
 			Block::default()
 				.borders(Borders::ALL)
-				.title(String::from(DEBUG_WINDOW_NAME)),
+				.border_style(border_style)
+				.title(format!(
+					"{} (last render {:.1}ms)",
+					DEBUG_WINDOW_NAME,
+					dash_state.last_render.as_secs_f64() * 1000.0
+				)),
 		)
 		.highlight_style(highlight_style);
 
@@ -147,7 +653,7 @@ fn old_draw_dash_horizontal<B: Backend>(
 	let size = f.size();
 	let chunks = Layout::default()
 		.direction(Direction::Vertical)
-		.constraints(constraints.as_ref())
+		.constraints(constraints.as_slice())
 		.split(size);
 
 	for (logfile, monitor) in monitors.iter_mut() {
@@ -164,7 +670,7 @@ fn old_draw_dash_horizontal<B: Backend>(
 			.items
 			.iter()
 			.map(|s| {
-				ListItem::new(vec![Spans::from(s.clone())])
+				ListItem::new(vec![Spans::from(s.text.to_string())])
 					.style(Style::default().fg(Color::Black).bg(Color::White))
 			})
 			.collect();
@@ -197,7 +703,7 @@ fn old_draw_dash_vertical<B: Backend>(
 	let size = f.size();
 	let chunks = Layout::default()
 		.direction(Direction::Horizontal)
-		.constraints(constraints.as_ref())
+		.constraints(constraints.as_slice())
 		.split(size);
 
 	for (logfile, monitor) in monitors.iter_mut() {
@@ -210,7 +716,7 @@ fn old_draw_dash_vertical<B: Backend>(
 			.items
 			.iter()
 			.map(|s| {
-				ListItem::new(vec![Spans::from(s.clone())])
+				ListItem::new(vec![Spans::from(s.text.to_string())])
 					.style(Style::default().fg(Color::Black).bg(Color::White))
 			})
 			.collect();
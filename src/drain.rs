@@ -0,0 +1,207 @@
+//! Log template mining
+//!
+//! Groups log lines into templates (message patterns) in the style of the
+//! Drain algorithm (He et al, "Drain: An Online Log Parsing Approach with
+//! Fixed Depth Tree", 2017). Variable tokens such as numbers, IDs, IP
+//! addresses and hex values are masked, then each line is matched to the
+//! most similar template with the same number of tokens and the same first
+//! token. Tokens which differ between a template and a matching line become
+//! wildcards.
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+pub static WILDCARD: &str = "<*>";
+
+/// A message pattern and the lines which have matched it
+pub struct Template {
+	pub id: usize,
+	pub tokens: Vec<String>,
+	pub count: usize,
+	pub first_seen: SystemTime,
+	pub last_seen: SystemTime,
+}
+
+impl Template {
+	pub fn text(&self) -> String {
+		self.tokens.join(" ")
+	}
+
+	/// Fraction of tokens equal to those of the template, and the number of wildcards
+	///
+	/// A token masked as a variable is equal to a wildcard, so lines made
+	/// mostly of variables still match their template.
+	fn similarity(&self, tokens: &[String]) -> (f64, usize) {
+		let mut equal = 0;
+		let mut wildcards = 0;
+		for (template_token, token) in self.tokens.iter().zip(tokens) {
+			if template_token == WILDCARD {
+				wildcards += 1;
+			}
+			if template_token == token {
+				equal += 1;
+			}
+		}
+		(equal as f64 / self.tokens.len() as f64, wildcards)
+	}
+}
+
+/// Clusters lines into templates as they arrive
+pub struct TemplateMiner {
+	pub templates: Vec<Template>, // In order of first appearance, indexed by id
+	groups: HashMap<(usize, String), Vec<usize>>, // (token count, first token) => template ids
+	similarity_threshold: f64,
+	max_templates: usize,
+}
+
+impl TemplateMiner {
+	pub fn new(similarity_threshold: f64, max_templates: usize) -> TemplateMiner {
+		TemplateMiner {
+			templates: Vec::new(),
+			groups: HashMap::new(),
+			similarity_threshold,
+			max_templates,
+		}
+	}
+
+	/// Add a line, returning the id of its template
+	///
+	/// Returns None for empty lines, or if the line would need a new
+	/// template and the maximum number of templates has been reached.
+	pub fn add(&mut self, line: &str) -> Option<usize> {
+		let tokens: Vec<String> = line.split_whitespace().map(mask_token).collect();
+		if tokens.is_empty() {
+			return None;
+		}
+		let now = SystemTime::now();
+
+		let group_key = (tokens.len(), tokens[0].clone());
+		let group = self.groups.entry(group_key).or_default();
+
+		let mut best: Option<(usize, f64, usize)> = None;
+		for id in group.iter() {
+			let (similarity, wildcards) = self.templates[*id].similarity(&tokens);
+			let better = match best {
+				Some((_, best_similarity, best_wildcards)) => {
+					similarity > best_similarity
+						|| (similarity == best_similarity && wildcards > best_wildcards)
+				}
+				None => true,
+			};
+			if better {
+				best = Some((*id, similarity, wildcards));
+			}
+		}
+
+		if let Some((id, similarity, _)) = best {
+			if similarity >= self.similarity_threshold {
+				let template = &mut self.templates[id];
+				for (template_token, token) in template.tokens.iter_mut().zip(tokens.iter()) {
+					if template_token != token {
+						*template_token = WILDCARD.to_string();
+					}
+				}
+				template.count += 1;
+				template.last_seen = now;
+				return Some(id);
+			}
+		}
+
+		if self.templates.len() >= self.max_templates {
+			return None;
+		}
+		let id = self.templates.len();
+		group.push(id);
+		self.templates.push(Template {
+			id,
+			tokens,
+			count: 1,
+			first_seen: now,
+			last_seen: now,
+		});
+		Some(id)
+	}
+}
+
+/// Replace a token with a wildcard if it looks like a variable
+///
+/// Surrounding punctuation is kept, and for key=value tokens only the
+/// value is considered.
+fn mask_token(token: &str) -> String {
+	if let Some(equals) = token.find('=') {
+		let (key, value) = token.split_at(equals + 1);
+		return format!("{}{}", key, mask_token(value));
+	}
+
+	let is_punctuation = |c: char| "()[]{}<>,;:'\"".contains(c);
+	let start = token.len() - token.trim_start_matches(is_punctuation).len();
+	let end = token.trim_end_matches(is_punctuation).len();
+	let core = &token[start..end.max(start)];
+	if is_variable(core) {
+		format!(
+			"{}{}{}",
+			&token[..start],
+			WILDCARD,
+			&token[end.max(start)..]
+		)
+	} else {
+		token.to_string()
+	}
+}
+
+fn is_variable(token: &str) -> bool {
+	if token.is_empty() || !token.chars().any(|c| c.is_ascii_digit()) {
+		return false;
+	}
+
+	let is_number = |s: &str| {
+		let s = s.trim_start_matches(['-', '+']);
+		!s.is_empty()
+			&& s.chars()
+				.all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+	};
+	// Numbers, optionally with a unit (e.g. 12ms, 3.5MB, 80%)
+	let digits_end = token
+		.find(|c: char| !(c.is_ascii_digit() || "+-.,".contains(c)))
+		.unwrap_or(token.len());
+	if is_number(&token[..digits_end])
+		&& token[digits_end..]
+			.chars()
+			.all(|c| c.is_ascii_alphabetic() || c == '%')
+		&& token.len() - digits_end <= 3
+	{
+		return true;
+	}
+
+	// Hex values and IDs, UUIDs, IP addresses (with optional port), times and dates
+	let hex = token.trim_start_matches("0x");
+	if hex.len() >= 4 && hex.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+		return true;
+	}
+	if token
+		.chars()
+		.all(|c| c.is_ascii_digit() || ".:/-_T+Z".contains(c))
+	{
+		return true;
+	}
+
+	// Anything else mostly made of digits (e.g. user12345, req-8f3a9b21c)
+	let digits = token.chars().filter(|c| c.is_ascii_digit()).count();
+	digits * 2 >= token.len()
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn lines_mostly_of_variables_share_a_template() {
+		let mut miner = super::TemplateMiner::new(0.5, 10);
+		for n in 1..=20 {
+			let line = format!("GET /api/{} 200 {}ms", n, n * 7);
+			assert_eq!(miner.add(&line), Some(0));
+		}
+		assert_eq!(miner.templates.len(), 1);
+		assert_eq!(miner.templates[0].count, 20);
+		assert_eq!(miner.templates[0].text(), "GET <*> <*> <*>");
+
+		assert_eq!(miner.add("POST /api/1 201 5ms"), Some(1));
+	}
+}
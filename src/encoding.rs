@@ -0,0 +1,146 @@
+//! Decoding logfile content which may not be valid UTF-8
+//!
+//! Bytes which can't be decoded are shown as the replacement character
+//! (U+FFFD) rather than causing an error. Content which looks binary is
+//! summarised as a hexdump of its start, so it doesn't fill a pane with
+//! garbage.
+use std::borrow::Cow;
+
+/// Character encoding of a logfile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+	Utf8,
+	Latin1,
+	Utf16,   // Little-endian unless there is a big-endian byte order mark
+	Utf16Be, // Found from a byte order mark by for_content()
+}
+
+impl Encoding {
+	pub fn variants() -> [&'static str; 3] {
+		["utf-8", "latin-1", "utf-16"]
+	}
+
+	/// The encoding of content which starts with head
+	///
+	/// Only the start of UTF-16 content has a byte order mark, so parts read
+	/// from further on need to be decoded knowing the order found here.
+	pub fn for_content(self, head: &[u8]) -> Encoding {
+		match self {
+			Encoding::Utf16 if head.starts_with(BOM_UTF16_BE) => Encoding::Utf16Be,
+			encoding => encoding,
+		}
+	}
+
+	/// The bytes which end a line
+	pub fn newline(self) -> &'static [u8] {
+		match self {
+			Encoding::Utf16 => b"\n\0",
+			Encoding::Utf16Be => b"\0\n",
+			_ => b"\n",
+		}
+	}
+}
+
+impl std::str::FromStr for Encoding {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"utf-8" | "utf8" => Ok(Encoding::Utf8),
+			"latin-1" | "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+			"utf-16" | "utf16" => Ok(Encoding::Utf16),
+			_ => Err(format!("unknown encoding: {}", s)),
+		}
+	}
+}
+
+/// How much of the start of the content is used to decide if it is binary
+pub const BINARY_SAMPLE: usize = 8192;
+
+/// Content with more than this proportion of unprintable characters is binary
+const BINARY_THRESHOLD: f64 = 0.1;
+
+/// Number of bytes shown in the summary of binary content
+const HEXDUMP_BYTES: usize = 256;
+
+const BOM_UTF8: &[u8] = b"\xef\xbb\xbf";
+const BOM_UTF16_LE: &[u8] = b"\xff\xfe";
+const BOM_UTF16_BE: &[u8] = b"\xfe\xff";
+
+/// Decode bytes to text, replacing anything which can't be decoded
+pub fn decode(bytes: &[u8], encoding: Encoding) -> Cow<'_, str> {
+	match encoding {
+		Encoding::Utf8 => String::from_utf8_lossy(bytes.strip_prefix(BOM_UTF8).unwrap_or(bytes)),
+		Encoding::Latin1 => Cow::Owned(bytes.iter().map(|b| *b as char).collect()),
+		Encoding::Utf16 | Encoding::Utf16Be => {
+			let (bytes, big_endian) = match bytes.strip_prefix(BOM_UTF16_BE) {
+				Some(rest) => (rest, true),
+				None if encoding == Encoding::Utf16Be => (bytes, true),
+				None => (bytes.strip_prefix(BOM_UTF16_LE).unwrap_or(bytes), false),
+			};
+			let units = bytes.chunks(2).map(|pair| match (pair, big_endian) {
+				([high, low], true) | ([low, high], false) => u16::from_be_bytes([*high, *low]),
+				_ => 0xfffd, // Odd byte at the end
+			});
+			Cow::Owned(
+				std::char::decode_utf16(units)
+					.map(|c| c.unwrap_or(std::char::REPLACEMENT_CHARACTER))
+					.collect(),
+			)
+		}
+	}
+}
+
+/// Decode the content of a logfile into lines, or summarise it if it looks binary
+pub fn decode_lines(bytes: &[u8], encoding: Encoding) -> Vec<String> {
+	if is_binary(bytes, encoding) {
+		return hexdump_summary(bytes, bytes.len() as u64);
+	}
+	decode(bytes, encoding).lines().map(String::from).collect()
+}
+
+/// True if the start of the content has NULs or many other unprintable characters
+pub fn is_binary(bytes: &[u8], encoding: Encoding) -> bool {
+	let sample = decode(&bytes[..bytes.len().min(BINARY_SAMPLE)], encoding);
+	let mut chars = 0;
+	let mut unprintable = 0;
+	for c in sample.chars() {
+		if c == '\0' {
+			return true;
+		}
+		chars += 1;
+		let printable = !c.is_control() || "\t\n\r\x0c\x1b".contains(c);
+		if !printable || c == std::char::REPLACEMENT_CHARACTER {
+			unprintable += 1;
+		}
+	}
+	chars > 0 && unprintable as f64 / chars as f64 > BINARY_THRESHOLD
+}
+
+/// Lines describing binary content, with a hexdump of its start
+///
+/// Only the start of the content need be given, with the size of all of it.
+pub fn hexdump_summary(bytes: &[u8], size: u64) -> Vec<String> {
+	let shown = bytes.len().min(HEXDUMP_BYTES);
+	let mut lines = vec![format!(
+		"binary content, {} bytes (first {} shown)",
+		size, shown
+	)];
+	for (row, chunk) in bytes[..shown].chunks(16).enumerate() {
+		let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+		let text: String = chunk
+			.iter()
+			.map(|b| match b {
+				0x20..=0x7e => *b as char,
+				_ => '.',
+			})
+			.collect();
+		lines.push(format!(
+			"{:08x}  {:<48} |{}|",
+			row * 16,
+			hex.join(" "),
+			text
+		));
+	}
+	lines
+}
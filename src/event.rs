@@ -4,37 +4,40 @@ use std::sync::{
 	Arc,
 };
 use std::thread;
-use std::time::Duration;
+use std::time::Instant;
 use tokio::sync::mpsc;
 
+use super::timer::Timer;
+
 use termion::event::Key;
 use termion::input::TermRead;
 
 pub enum Event<I> {
 	Input(I),
-	Tick,
+	Resize,
+	Tick, // Woken by the timer (see wake_at())
 }
 
-/// A small event handler that wrap termion input and tick events. Each event
-/// type is handled in its own thread and returned to a common `Receiver`
+/// A small event handler that wrap termion input, terminal resize and timer
+/// events. Each event type is handled in its own thread and returned to a
+/// common `Receiver`
 pub struct Events {
 	pub rx: mpsc::UnboundedReceiver<Event<Key>>,
 	input_handle: thread::JoinHandle<()>,
 	ignore_exit_key: Arc<AtomicBool>,
-	tick_handle: thread::JoinHandle<()>,
+	resize_handle: thread::JoinHandle<()>,
+	timer: Timer,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Config {
 	pub exit_key: Key,
-	pub tick_rate: Duration,
 }
 
 impl Default for Config {
 	fn default() -> Config {
 		Config {
 			exit_key: Key::Char('q'),
-			tick_rate: Duration::from_millis(250),
 		}
 	}
 }
@@ -65,22 +68,39 @@ impl Events {
 				}
 			})
 		};
-		let tick_handle = {
-			thread::spawn(move || loop {
-				if tx.send(Event::Tick).is_err() {
-					break;
+		let resize_handle = {
+			let tx = tx.clone();
+			thread::spawn(move || {
+				// termion has no resize event, so watch for the signal instead
+				let signals = match signal_hook::iterator::Signals::new([signal_hook::SIGWINCH]) {
+					Ok(signals) => signals,
+					Err(err) => {
+						eprintln!("{}", err);
+						return;
+					}
+				};
+				for _ in signals.forever() {
+					if tx.send(Event::Resize).is_err() {
+						return;
+					}
 				}
-				thread::sleep(config.tick_rate);
 			})
 		};
+		let timer = Timer::new(tx, || Event::Tick);
 		Events {
 			rx,
 			ignore_exit_key,
 			input_handle,
-			tick_handle,
+			resize_handle,
+			timer,
 		}
 	}
 
+	/// Send a Tick at the given time, unless one is already due sooner
+	pub fn wake_at(&self, when: Instant) {
+		self.timer.wake_at(when);
+	}
+
 	pub fn disable_exit_key(&mut self) {
 		self.ignore_exit_key.store(true, Ordering::Relaxed);
 	}
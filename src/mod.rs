@@ -1,3 +1,11 @@
+pub mod ansi;
+pub mod drain;
+pub mod encoding;
+pub mod source;
+pub mod syslog;
+pub mod tail;
+pub mod timer;
+pub mod timestamp;
 pub mod util;
 
 #[cfg(feature = "termion")]
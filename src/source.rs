@@ -0,0 +1,125 @@
+//! Identity of logfiles, however their paths are written
+//!
+//! A logfile named on the command line may be reported back by the file
+//! watcher with a different path: absolute rather than relative, through
+//! a symlink, or with `..` segments resolved. SourceIndex maps all the
+//! paths a logfile is known by to its name, and falls back to comparing
+//! device and inode for paths it hasn't seen before.
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Device and inode of a file, where supported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId {
+	pub device: u64,
+	pub inode: u64,
+}
+
+impl FileId {
+	#[cfg(unix)]
+	pub fn of(path: &Path) -> Option<FileId> {
+		use std::os::unix::fs::MetadataExt;
+		let metadata = std::fs::metadata(path).ok()?;
+		Some(FileId {
+			device: metadata.dev(),
+			inode: metadata.ino(),
+		})
+	}
+
+	#[cfg(not(unix))]
+	pub fn of(_path: &Path) -> Option<FileId> {
+		None
+	}
+}
+
+/// Names of logfiles, found by any path which identifies them
+#[derive(Default)]
+pub struct SourceIndex {
+	by_path: HashMap<PathBuf, String>,
+	by_file: HashMap<FileId, String>,
+}
+
+impl SourceIndex {
+	pub fn new() -> SourceIndex {
+		SourceIndex::default()
+	}
+
+	/// Add a logfile by the name it was given
+	///
+	/// If it is the same file as one added already, returns the name of that
+	/// one. Each keeps the paths it was given, as the watcher follows each
+	/// separately.
+	pub fn add(&mut self, name: &str) -> Option<String> {
+		let path = Path::new(name);
+		let mut paths = vec![path.to_path_buf()];
+		paths.extend(absolute_path(path));
+		paths.extend(path.canonicalize().ok());
+
+		let existing = paths
+			.iter()
+			.find_map(|path| self.by_path.get(path))
+			.or_else(|| FileId::of(path).and_then(|id| self.by_file.get(&id)))
+			.filter(|existing| existing.as_str() != name)
+			.cloned();
+
+		for path in paths {
+			self.by_path.entry(path).or_insert_with(|| name.to_string());
+		}
+		if let Some(id) = FileId::of(path) {
+			self.by_file.entry(id).or_insert_with(|| name.to_string());
+		}
+		existing
+	}
+
+	/// Add another path for a logfile, such as the one the watcher reports it by
+	pub fn add_alias(&mut self, path: &Path, name: &str) {
+		self.by_path
+			.entry(path.to_path_buf())
+			.or_insert_with(|| name.to_string());
+	}
+
+	/// Forget all the paths of a logfile which is no longer followed
+	pub fn remove(&mut self, name: &str) {
+		self.by_path.retain(|_, path_name| path_name != name);
+		self.by_file.retain(|_, file_name| file_name != name);
+	}
+
+	/// The name of the logfile at a path, if known
+	///
+	/// Paths found by resolving them are remembered, so that later lookups
+	/// are a single hash lookup.
+	pub fn lookup(&mut self, path: &Path) -> Option<String> {
+		if let Some(name) = self.by_path.get(path) {
+			return Some(name.clone());
+		}
+
+		let name = absolute_path(path)
+			.into_iter()
+			.chain(path.canonicalize().ok())
+			.find_map(|resolved| self.by_path.get(&resolved))
+			.or_else(|| FileId::of(path).and_then(|id| self.by_file.get(&id)))
+			.cloned()?;
+		self.add_alias(path, &name);
+		Some(name)
+	}
+}
+
+/// An absolute path with `.` and `..` removed, without following symlinks
+pub fn absolute_path(path: &Path) -> Option<PathBuf> {
+	let path = match path.is_absolute() {
+		true => path.to_path_buf(),
+		false => std::env::current_dir().ok()?.join(path),
+	};
+
+	let mut absolute = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::CurDir => (),
+			Component::ParentDir => {
+				absolute.pop();
+			}
+			component => absolute.push(component),
+		}
+	}
+	Some(absolute)
+}
@@ -0,0 +1,421 @@
+//! Built-in syslog receiver
+//!
+//! Listens for syslog messages over UDP and/or TCP and parses them
+//! according to RFC 5424 or, failing that, RFC 3164 (BSD syslog).
+//! Parsed messages are delivered on a channel in the same way as
+//! terminal input events (see src/event.rs).
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::thread;
+use tokio::sync::mpsc;
+
+static SEVERITY_NAMES: [&str; 8] = [
+	"emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// A single syslog message
+#[derive(Debug, Clone)]
+pub struct SyslogMessage {
+	pub facility: u8,
+	pub severity: u8,
+	pub timestamp: Option<String>,
+	pub hostname: Option<String>,
+	pub app_name: Option<String>,
+	pub procid: Option<String>,
+	pub msgid: Option<String>,
+	pub message: String,
+}
+
+impl SyslogMessage {
+	/// Parse a message as RFC 5424, falling back to RFC 3164
+	///
+	/// Anything which can't be parsed is kept as the message text
+	/// with the default priority (user.notice).
+	pub fn parse(text: &str) -> SyslogMessage {
+		let text = text.trim_end_matches(&['\n', '\r', '\0'][..]);
+		let (pri, rest) = match parse_pri(text) {
+			Some((pri, rest)) => (pri, rest),
+			None => return SyslogMessage::unparsed(13, text),
+		};
+
+		if let Some(message) = parse_rfc5424(pri, rest) {
+			return message;
+		}
+		parse_rfc3164(pri, rest)
+	}
+
+	fn unparsed(pri: u8, text: &str) -> SyslogMessage {
+		SyslogMessage {
+			facility: pri >> 3,
+			severity: pri & 7,
+			timestamp: None,
+			hostname: None,
+			app_name: None,
+			procid: None,
+			msgid: None,
+			message: text.to_string(),
+		}
+	}
+
+	pub fn severity_name(&self) -> &'static str {
+		SEVERITY_NAMES[(self.severity & 7) as usize]
+	}
+
+	/// Format the message as a single line in the style of a syslog file
+	pub fn to_line(&self) -> String {
+		let mut line = String::new();
+		for field in [&self.timestamp, &self.hostname].iter().copied().flatten() {
+			line.push_str(field);
+			line.push(' ');
+		}
+
+		if let Some(app_name) = &self.app_name {
+			line.push_str(app_name);
+			if let Some(procid) = &self.procid {
+				line.push_str(&format!("[{}]", procid));
+			}
+			line.push_str(": ");
+		}
+		line.push_str(&format!("<{}> {}", self.severity_name(), self.message));
+		line
+	}
+}
+
+fn parse_pri(text: &str) -> Option<(u8, &str)> {
+	if !text.starts_with('<') {
+		return None;
+	}
+	let end = text.find('>')?;
+	if !(2..=4).contains(&end) {
+		return None;
+	}
+	let pri = text[1..end].parse::<u8>().ok()?;
+	if pri > 191 {
+		return None;
+	}
+	Some((pri, &text[end + 1..]))
+}
+
+fn nil_or_value(field: &str) -> Option<String> {
+	if field == "-" {
+		None
+	} else {
+		Some(field.to_string())
+	}
+}
+
+/// RFC 5424: VERSION SP TIMESTAMP SP HOSTNAME SP APP-NAME SP PROCID SP MSGID SP SD [SP MSG]
+fn parse_rfc5424(pri: u8, text: &str) -> Option<SyslogMessage> {
+	let mut fields = text.splitn(7, ' ');
+	let version = fields.next()?;
+	if version.is_empty() || !version.chars().all(|c| c.is_ascii_digit()) {
+		return None;
+	}
+	let timestamp = fields.next()?;
+	let hostname = fields.next()?;
+	let app_name = fields.next()?;
+	let procid = fields.next()?;
+	let msgid = fields.next()?;
+	let rest = fields.next().unwrap_or("");
+
+	let (structured_data, message) = split_structured_data(rest)?;
+	let mut message = message.trim_start_matches('\u{feff}').to_string();
+	if structured_data != "-" {
+		message = format!("{} {}", structured_data, message)
+			.trim_end()
+			.to_string();
+	}
+
+	Some(SyslogMessage {
+		facility: pri >> 3,
+		severity: pri & 7,
+		timestamp: nil_or_value(timestamp),
+		hostname: nil_or_value(hostname),
+		app_name: nil_or_value(app_name),
+		procid: nil_or_value(procid),
+		msgid: nil_or_value(msgid),
+		message,
+	})
+}
+
+/// Split STRUCTURED-DATA from MSG, allowing for escaped characters in PARAM-VALUEs
+fn split_structured_data(text: &str) -> Option<(&str, &str)> {
+	if let Some(message) = text.strip_prefix('-') {
+		return Some(("-", message.trim_start_matches(' ')));
+	}
+	if !text.starts_with('[') {
+		return None;
+	}
+
+	let mut in_element = false;
+	let mut in_value = false;
+	let mut escaped = false;
+	for (i, c) in text.char_indices() {
+		if escaped {
+			escaped = false;
+			continue;
+		}
+		match c {
+			'\\' if in_value => escaped = true,
+			'"' if in_element => in_value = !in_value,
+			'[' if !in_value => in_element = true,
+			']' if !in_value => in_element = false,
+			' ' if !in_element => return Some((&text[..i], &text[i + 1..])),
+			_ if !in_element => return None,
+			_ => (),
+		}
+	}
+	if in_element {
+		None
+	} else {
+		Some((text, ""))
+	}
+}
+
+/// RFC 3164: TIMESTAMP SP HOSTNAME SP TAG[PID]: MSG
+///
+/// Senders vary in what they include, so every part is optional.
+fn parse_rfc3164(pri: u8, text: &str) -> SyslogMessage {
+	let mut message = SyslogMessage::unparsed(pri, text);
+	let mut rest = text;
+
+	// Timestamp: "Mmm dd hh:mm:ss" (day is space padded)
+	if rest.len() >= 15 && rest.is_char_boundary(15) {
+		let candidate = &rest[..15];
+		let bytes = candidate.as_bytes();
+		if bytes[3] == b' ' && bytes[9] == b':' && bytes[12] == b':' {
+			message.timestamp = Some(candidate.to_string());
+			rest = rest[15..].trim_start_matches(' ');
+		}
+	}
+
+	// Hostname is present if the first word isn't already the tag
+	let first_word_end = rest.find(' ').unwrap_or(rest.len());
+	let first_word = &rest[..first_word_end];
+	if message.timestamp.is_some() && !first_word.is_empty() && !is_tag(first_word) {
+		message.hostname = Some(first_word.to_string());
+		rest = rest[first_word_end..].trim_start_matches(' ');
+	}
+
+	// Tag, with optional PID
+	let tag_end = rest.find(' ').unwrap_or(rest.len());
+	let tag = &rest[..tag_end];
+	if is_tag(tag) {
+		let tag = tag.trim_end_matches(':');
+		match (tag.find('['), tag.ends_with(']')) {
+			(Some(open), true) => {
+				message.app_name = Some(tag[..open].to_string());
+				message.procid = Some(tag[open + 1..tag.len() - 1].to_string());
+			}
+			_ => message.app_name = Some(tag.to_string()),
+		}
+		rest = rest[tag_end..].trim_start_matches(' ');
+	}
+
+	message.message = rest.to_string();
+	message
+}
+
+fn is_tag(word: &str) -> bool {
+	word.len() > 1 && word.ends_with(':')
+}
+
+/// Receives syslog messages on UDP and/or TCP sockets
+///
+/// Each socket (and each TCP connection) is handled in its own thread,
+/// with parsed messages returned to a common `Receiver`.
+pub struct SyslogReceiver {
+	pub rx: mpsc::UnboundedReceiver<SyslogMessage>,
+}
+
+impl SyslogReceiver {
+	pub fn new(udp_addr: Option<&str>, tcp_addr: Option<&str>) -> io::Result<SyslogReceiver> {
+		let (tx, rx) = mpsc::unbounded_channel();
+
+		if let Some(addr) = udp_addr {
+			let socket = UdpSocket::bind(addr)?;
+			let tx = tx.clone();
+			thread::spawn(move || {
+				let mut buf = [0u8; 65536];
+				while let Ok((len, _peer)) = socket.recv_from(&mut buf) {
+					let text = String::from_utf8_lossy(&buf[..len]);
+					if tx.send(SyslogMessage::parse(&text)).is_err() {
+						return;
+					}
+				}
+			});
+		}
+
+		if let Some(addr) = tcp_addr {
+			let listener = TcpListener::bind(addr)?;
+			let tx = tx.clone();
+			thread::spawn(move || {
+				for stream in listener.incoming().flatten() {
+					let tx = tx.clone();
+					thread::spawn(move || receive_tcp(stream, tx));
+				}
+			});
+		}
+
+		Ok(SyslogReceiver { rx })
+	}
+
+	/// Wait for the next message
+	///
+	/// Never returns if the receiver is absent or all its sockets have closed,
+	/// so it can be used alongside other futures in a `select!`
+	pub async fn next(receiver: &mut Option<SyslogReceiver>) -> SyslogMessage {
+		if let Some(receiver) = receiver {
+			if let Some(message) = receiver.rx.recv().await {
+				return message;
+			}
+		}
+		futures::future::pending().await
+	}
+}
+
+/// Largest TCP frame accepted, as is usual for RFC 6587 receivers
+const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+/// Read messages from a TCP connection
+///
+/// The connection is dropped if a frame is malformed or too large.
+fn receive_tcp(stream: TcpStream, tx: mpsc::UnboundedSender<SyslogMessage>) {
+	let mut reader = BufReader::new(stream);
+	while let Some(frame) = read_frame(&mut reader) {
+		let text = String::from_utf8_lossy(&frame);
+		if !text.trim().is_empty() && tx.send(SyslogMessage::parse(&text)).is_err() {
+			return;
+		}
+	}
+}
+
+/// Read the next frame, or None at the end of the stream or on an error
+///
+/// Supports both octet-counting and newline framing (RFC 6587).
+fn read_frame<R: BufRead>(reader: &mut R) -> Option<Vec<u8>> {
+	let octet_counted = match reader.fill_buf().ok()?.first() {
+		Some(c) => c.is_ascii_digit(),
+		None => return None,
+	};
+
+	let mut frame = Vec::new();
+	if octet_counted {
+		let mut count = Vec::new();
+		reader.by_ref().take(20).read_until(b' ', &mut count).ok()?;
+		let count = String::from_utf8_lossy(&count);
+		let count = count.strip_suffix(' ')?.parse::<usize>().ok()?;
+		if count > MAX_FRAME_SIZE {
+			return None;
+		}
+		frame.resize(count, 0);
+		reader.read_exact(&mut frame).ok()?;
+	} else {
+		let limit = MAX_FRAME_SIZE as u64 + 1;
+		reader
+			.by_ref()
+			.take(limit)
+			.read_until(b'\n', &mut frame)
+			.ok()?;
+		if frame.len() > MAX_FRAME_SIZE {
+			return None;
+		}
+	}
+	Some(frame)
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn parses_rfc5424() {
+		let message = super::SyslogMessage::parse(
+			"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 \
+			 [exampleSDID@32473 iut=\"3\" eventSource=\"App]lication\"] An application event",
+		);
+		assert_eq!((message.facility, message.severity), (20, 5));
+		assert_eq!(
+			message.timestamp.as_deref(),
+			Some("2003-10-11T22:14:15.003Z")
+		);
+		assert_eq!(message.hostname.as_deref(), Some("mymachine.example.com"));
+		assert_eq!(message.app_name.as_deref(), Some("evntslog"));
+		assert_eq!(message.procid, None);
+		assert_eq!(message.msgid.as_deref(), Some("ID47"));
+		assert_eq!(
+			message.message,
+			"[exampleSDID@32473 iut=\"3\" eventSource=\"App]lication\"] An application event"
+		);
+
+		let message = super::SyslogMessage::parse("<34>1 - - su 42 - - \u{feff}'su root' failed\n");
+		assert_eq!(message.timestamp, None);
+		assert_eq!(message.procid.as_deref(), Some("42"));
+		assert_eq!(message.message, "'su root' failed");
+	}
+
+	#[test]
+	fn parses_rfc3164() {
+		let message =
+			super::SyslogMessage::parse("<34>Oct 11 22:14:15 mymachine su[123]: 'su root' failed");
+		assert_eq!((message.facility, message.severity), (4, 2));
+		assert_eq!(message.timestamp.as_deref(), Some("Oct 11 22:14:15"));
+		assert_eq!(message.hostname.as_deref(), Some("mymachine"));
+		assert_eq!(message.app_name.as_deref(), Some("su"));
+		assert_eq!(message.procid.as_deref(), Some("123"));
+		assert_eq!(message.message, "'su root' failed");
+		assert_eq!(
+			message.to_line(),
+			"Oct 11 22:14:15 mymachine su[123]: <crit> 'su root' failed"
+		);
+
+		let message = super::SyslogMessage::parse("<13>Oct  1 09:00:00 cron: job done");
+		assert_eq!(message.hostname, None);
+		assert_eq!(message.app_name.as_deref(), Some("cron"));
+		assert_eq!(message.message, "job done");
+	}
+
+	#[test]
+	fn malformed_pri_is_kept_as_text() {
+		for text in &["no pri", "<>x", "<192>x", "<12345>x", "<1a>x", "13>x"] {
+			let message = super::SyslogMessage::parse(text);
+			assert_eq!((message.facility, message.severity), (1, 5), "{}", text);
+			assert_eq!(message.message, *text);
+		}
+	}
+
+	#[test]
+	fn reads_octet_counted_and_newline_frames() {
+		let mut stream: &[u8] = b"5 <13>a9 <13>b\nc\nd\n<13>e\n";
+		assert_eq!(
+			super::read_frame(&mut stream).as_deref(),
+			Some(&b"<13>a"[..])
+		);
+		assert_eq!(
+			super::read_frame(&mut stream).as_deref(),
+			Some(&b"<13>b\nc\nd"[..])
+		);
+		assert_eq!(super::read_frame(&mut stream).as_deref(), Some(&b"\n"[..]));
+		assert_eq!(
+			super::read_frame(&mut stream).as_deref(),
+			Some(&b"<13>e\n"[..])
+		);
+		assert_eq!(super::read_frame(&mut stream), None);
+	}
+
+	#[test]
+	fn drops_connection_on_bad_frames() {
+		let oversized = format!("{} <13>", super::MAX_FRAME_SIZE + 1);
+		let unterminated = "9".repeat(100);
+		let long_line = "x".repeat(super::MAX_FRAME_SIZE + 1);
+		for text in &[
+			&oversized,
+			"999999999999 <13>",
+			&unterminated,
+			"12x <13>",
+			"9 short",
+			&long_line,
+		] {
+			let mut stream = text.as_bytes();
+			assert_eq!(super::read_frame(&mut stream), None, "{:.20}", text);
+		}
+	}
+}
@@ -0,0 +1,320 @@
+//! Reading the end of large logfiles quickly
+//!
+//! read_last_lines() seeks back from the end of a file, so the time taken
+//! depends on the number of lines wanted rather than the size of the file.
+//! The rest of the file can then be read a little at a time by a Scan, for
+//! anything which needs the whole history, and lines added later are read
+//! by a Follow.
+//!
+//! Lines end in the newline of their encoding, which for UTF-16 is two
+//! bytes long and is only looked for at even offsets.
+use std::fs::{File, Metadata};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Size of the blocks read when seeking back from the end of a file
+const TAIL_BLOCK: u64 = 64 * 1024;
+
+/// Read the start of a file, up to max_bytes
+pub fn read_head(file: &mut File, max_bytes: usize) -> io::Result<Vec<u8>> {
+	let mut head = Vec::new();
+	file.seek(SeekFrom::Start(0))?;
+	file.by_ref()
+		.take(max_bytes as u64)
+		.read_to_end(&mut head)?;
+	Ok(head)
+}
+
+/// Read the last max_lines lines of a file, with lines ending in newline
+///
+/// Returns the bytes of those lines and the offset in the file at which
+/// they start.
+pub fn read_last_lines(
+	file: &mut File,
+	max_lines: usize,
+	newline: &[u8],
+) -> io::Result<(Vec<u8>, u64)> {
+	let unit = newline.len();
+	let size = file.metadata()?.len();
+	let mut start = size;
+	let mut blocks: Vec<Vec<u8>> = Vec::new();
+	let mut newlines = 0;
+
+	while start > 0 && max_lines > 0 {
+		let block_start = start.saturating_sub(TAIL_BLOCK) / unit as u64 * unit as u64;
+		let mut block = vec![0; (start - block_start) as usize];
+		file.seek(SeekFrom::Start(block_start))?;
+		file.read_exact(&mut block)?;
+
+		// The newline ending the last line doesn't start another
+		let at_end = block_start + block.len() as u64 == size;
+		let end = match at_end && ends_with_line(&block, newline) {
+			true => block.len() - unit,
+			false => block.len(),
+		};
+		let line_start = block[..end].chunks_exact(unit).rposition(|chunk| {
+			newlines += (chunk == newline) as usize;
+			newlines == max_lines
+		});
+		match line_start {
+			Some(i) => {
+				let i = (i + 1) * unit;
+				start = block_start + i as u64;
+				blocks.push(block.split_off(i));
+				break;
+			}
+			None => {
+				start = block_start;
+				blocks.push(block);
+			}
+		}
+	}
+
+	let tail = blocks.into_iter().rev().flatten().collect();
+	Ok((tail, start))
+}
+
+/// Read the next line into line, without its line ending
+///
+/// Returns the number of bytes read, which is zero at the end of the content.
+pub fn read_line<R: BufRead>(
+	reader: &mut R,
+	newline: &[u8],
+	line: &mut Vec<u8>,
+) -> io::Result<usize> {
+	line.clear();
+	let unit = newline.len();
+	let mut read = 0;
+	loop {
+		let buffer = reader.fill_buf()?;
+		if buffer.is_empty() {
+			break;
+		}
+
+		// A unit split across buffers is completed before looking further
+		let (taken, found) = match line.len() % unit {
+			0 => match buffer.chunks_exact(unit).position(|chunk| chunk == newline) {
+				Some(i) => ((i + 1) * unit, true),
+				None => (buffer.len(), false),
+			},
+			partial => {
+				let taken = (unit - partial).min(buffer.len());
+				line.extend_from_slice(&buffer[..taken]);
+				reader.consume(taken);
+				read += taken;
+				if ends_with_line(line, newline) {
+					break;
+				}
+				continue;
+			}
+		};
+		line.extend_from_slice(&buffer[..taken]);
+		reader.consume(taken);
+		read += taken;
+		if found {
+			break;
+		}
+	}
+
+	let without_ending = without_line_ending(line, newline).len();
+	line.truncate(without_ending);
+	Ok(read)
+}
+
+/// A line without its newline, or carriage return and newline
+fn without_line_ending<'a>(line: &'a [u8], newline: &[u8]) -> &'a [u8] {
+	let mut line = line;
+	if ends_with_line(line, newline) {
+		line = &line[..line.len() - newline.len()];
+	}
+	let carriage_return: Vec<u8> = newline
+		.iter()
+		.map(|b| if *b == b'\n' { b'\r' } else { *b })
+		.collect();
+	if ends_with_line(line, &carriage_return) {
+		line = &line[..line.len() - newline.len()];
+	}
+	line
+}
+
+/// True if content ends with newline, at an offset which is a multiple of its length
+// is_multiple_of() would need Rust 1.87
+#[allow(unknown_lints, clippy::manual_is_multiple_of)]
+fn ends_with_line(content: &[u8], newline: &[u8]) -> bool {
+	content.len() % newline.len() == 0 && content.ends_with(newline)
+}
+
+/// Reading of a file from the start up to a given offset, a little at a time
+pub struct Scan {
+	reader: BufReader<File>,
+	newline: &'static [u8],
+	position: u64,
+	end: u64,
+}
+
+impl Scan {
+	/// Scan the file from its start up to end, with lines ending in newline
+	pub fn new(mut file: File, end: u64, newline: &'static [u8]) -> io::Result<Scan> {
+		file.seek(SeekFrom::Start(0))?;
+		Ok(Scan {
+			reader: BufReader::new(file),
+			newline,
+			position: 0,
+			end,
+		})
+	}
+
+	/// Pass lines (without line endings) to f until the time allowed is up
+	pub fn scan_for(&mut self, allowed: Duration, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+		let started = Instant::now();
+		let mut line = Vec::new();
+		while !self.is_finished() && started.elapsed() < allowed {
+			let remaining = self.end - self.position;
+			let mut reader = self.reader.by_ref().take(remaining);
+			let read = read_line(&mut reader, self.newline, &mut line)?;
+			if read == 0 {
+				self.end = self.position; // The file has been truncated
+				break;
+			}
+			self.position += read as u64;
+			f(&line);
+		}
+		Ok(())
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.position >= self.end
+	}
+
+	/// Percentage of the file scanned
+	pub fn percent_done(&self) -> u64 {
+		match self.end {
+			0 => 100,
+			end => self.position * 100 / end,
+		}
+	}
+}
+
+/// Identity of a file (its inode, where supported), to notice it being replaced
+pub fn file_id(metadata: &Metadata) -> Option<u64> {
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::MetadataExt;
+		Some(metadata.ino())
+	}
+	#[cfg(not(unix))]
+	{
+		let _ = metadata;
+		None
+	}
+}
+
+/// Reading of the lines added to a file after a given offset
+///
+/// Only complete lines are read, so a line still being written is read
+/// once it has its newline. If the file is truncated, reading starts again
+/// from its start, and if it is replaced (rotated), from the start of the
+/// new file once the rest of the old one has been read.
+pub struct Follow {
+	path: PathBuf,
+	file: Option<File>, // Until the file is opened, or after it is replaced
+	id: Option<u64>,
+	newline: &'static [u8],
+	position: u64,
+	partial: Vec<u8>, // The start of a line still being written
+}
+
+impl Follow {
+	/// Follow the file at path from the given offset, with lines ending in newline
+	pub fn new(path: &Path, position: u64, newline: &'static [u8]) -> Follow {
+		Follow {
+			path: path.to_path_buf(),
+			file: None,
+			id: None,
+			newline,
+			position,
+			partial: Vec::new(),
+		}
+	}
+
+	/// Pass each line (without its line ending) added since the last read to f
+	///
+	/// It is ok for the file not to exist (yet).
+	pub fn read_lines(&mut self, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+		let mut reopened = false;
+		loop {
+			let file = match self.file.as_mut() {
+				Some(file) => file,
+				None => {
+					let mut file = match File::open(&self.path) {
+						Ok(file) => file,
+						Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+						Err(e) => return Err(e),
+					};
+					self.id = file_id(&file.metadata()?);
+					file.seek(SeekFrom::Start(self.position))?;
+					self.file.get_or_insert(file)
+				}
+			};
+
+			let read = file.read_to_end(&mut self.partial)?;
+			self.position += read as u64;
+			let unit = self.newline.len();
+			let mut line_start = 0;
+			for (i, chunk) in self.partial.chunks_exact(unit).enumerate() {
+				if chunk == self.newline {
+					let line_end = (i + 1) * unit;
+					f(without_line_ending(
+						&self.partial[line_start..line_end],
+						self.newline,
+					));
+					line_start = line_end;
+				}
+			}
+			self.partial.drain(..line_start);
+			if read > 0 || reopened {
+				return Ok(());
+			}
+
+			// Nothing new, so check whether the file has been replaced or truncated
+			let metadata = match std::fs::metadata(&self.path) {
+				Ok(metadata) => metadata,
+				Err(_e) => return Ok(()), // Removed, perhaps to be replaced
+			};
+			if file_id(&metadata) != self.id {
+				self.file = None;
+			} else if metadata.len() < self.position {
+				file.seek(SeekFrom::Start(0))?;
+			} else {
+				return Ok(());
+			}
+			self.position = 0;
+			self.partial.clear();
+			reopened = true;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn utf16_lines_read_across_small_buffers() {
+		let newline = [b'\n', 0];
+		let bytes: Vec<u8> = "\u{0a41}\u{0100}é\r\nsecond\nlast"
+			.encode_utf16()
+			.flat_map(|unit| unit.to_le_bytes())
+			.collect();
+		let mut reader = std::io::BufReader::with_capacity(3, bytes.as_slice());
+		let mut line = Vec::new();
+		let mut texts = Vec::new();
+		while super::read_line(&mut reader, &newline, &mut line).unwrap() > 0 {
+			let units: Vec<u16> = line
+				.chunks_exact(2)
+				.map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+				.collect();
+			texts.push(String::from_utf16(&units).unwrap());
+		}
+		assert_eq!(texts, ["\u{0a41}\u{0100}é", "second", "last"]);
+	}
+}
@@ -0,0 +1,53 @@
+//! Waking the event loop at a time rather than on a regular tick
+//!
+//! The dashboard is drawn when lines arrive or keys are pressed, so the
+//! event loop only needs waking for things which depend on the time: a
+//! frame held back by --max-fps, activity strips and ages moving on, or
+//! replayed lines falling due. A Timer sends a single event at the earliest
+//! time asked for, and nothing at all while there is none.
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+use tokio::sync::mpsc::UnboundedSender;
+
+pub struct Timer {
+	requests: mpsc::Sender<Instant>,
+}
+
+impl Timer {
+	/// Start a timer which sends the event made by wake to tx when due
+	pub fn new<E: Send + 'static>(tx: UnboundedSender<E>, wake: fn() -> E) -> Timer {
+		let (requests, rx) = mpsc::channel::<Instant>();
+		thread::spawn(move || {
+			let mut due: Option<Instant> = None;
+			loop {
+				let request = match due {
+					Some(when) => {
+						match rx.recv_timeout(when.saturating_duration_since(Instant::now())) {
+							Ok(request) => request,
+							Err(mpsc::RecvTimeoutError::Timeout) => {
+								due = None;
+								if tx.send(wake()).is_err() {
+									return;
+								}
+								continue;
+							}
+							Err(mpsc::RecvTimeoutError::Disconnected) => return,
+						}
+					}
+					None => match rx.recv() {
+						Ok(request) => request,
+						Err(_) => return,
+					},
+				};
+				due = Some(due.map_or(request, |when| when.min(request)));
+			}
+		});
+		Timer { requests }
+	}
+
+	/// Wake the event loop at the given time, unless already due to wake sooner
+	pub fn wake_at(&self, when: Instant) {
+		let _ = self.requests.send(when);
+	}
+}
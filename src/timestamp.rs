@@ -0,0 +1,159 @@
+//! Timestamps in log lines and on the command line
+//!
+//! Recognises the formats most logfiles use: ISO 8601 / RFC 3339 (with or
+//! without a time zone), traditional syslog ("Jan  2 03:04:05") and the
+//! Common Log Format used by web servers ("[02/Jan/2024:03:04:05 +0000]").
+//! Times without a time zone are taken to be local time.
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+/// How far into a line to look for a timestamp
+const MAX_TIMESTAMP_OFFSET: usize = 100;
+
+static ZONED_FORMATS: [&str; 4] = [
+	"%Y-%m-%dT%H:%M:%S%.f%:z",
+	"%Y-%m-%dT%H:%M:%S%.f%z",
+	"%Y-%m-%d %H:%M:%S%.f%:z",
+	"%d/%b/%Y:%H:%M:%S %z",
+];
+
+static UTC_FORMATS: [&str; 1] = ["%Y-%m-%dT%H:%M:%S%.fZ"];
+
+static LOCAL_FORMATS: [&str; 4] = [
+	"%Y-%m-%dT%H:%M:%S%.f",
+	"%Y-%m-%d %H:%M:%S%.f",
+	"%Y/%m/%d %H:%M:%S%.f",
+	"%d/%b/%Y:%H:%M:%S",
+];
+
+/// The time a line was logged, if it starts with a timestamp (or has one in
+/// brackets near the start)
+pub fn parse_line_timestamp(text: &str) -> Option<SystemTime> {
+	let head = match text.char_indices().nth(MAX_TIMESTAMP_OFFSET) {
+		Some((end, _)) => &text[..end],
+		None => text,
+	};
+
+	let mut candidates = vec![head.trim_start()];
+	for (i, _) in head.match_indices('[') {
+		candidates.push(&head[i + 1..]);
+	}
+	candidates
+		.into_iter()
+		.filter(|candidate| candidate.starts_with(|c: char| c.is_ascii_alphanumeric()))
+		.find_map(parse_timestamp_prefix)
+}
+
+/// A timestamp at the start of text, which may be followed by anything
+fn parse_timestamp_prefix(text: &str) -> Option<SystemTime> {
+	if text.starts_with(|c: char| c.is_ascii_digit()) {
+		for format in ZONED_FORMATS.iter() {
+			if let Ok((time, _)) = DateTime::<FixedOffset>::parse_and_remainder(text, format) {
+				return Some(time.into());
+			}
+		}
+		for format in UTC_FORMATS.iter() {
+			if let Ok((time, _)) = NaiveDateTime::parse_and_remainder(text, format) {
+				return Some(Utc.from_utc_datetime(&time).into());
+			}
+		}
+		for format in LOCAL_FORMATS.iter() {
+			if let Ok((time, _)) = NaiveDateTime::parse_and_remainder(text, format) {
+				return local_time(&time);
+			}
+		}
+		None
+	} else {
+		parse_syslog_timestamp(text)
+	}
+}
+
+/// A traditional syslog timestamp, which has no year
+///
+/// The current year is assumed unless that would put the time more than a
+/// day in the future, as happens when reading December's logs in January.
+fn parse_syslog_timestamp(text: &str) -> Option<SystemTime> {
+	let now = Local::now();
+	let with_year = format!("{} {}", now.year(), text);
+	let (time, _) = NaiveDateTime::parse_and_remainder(&with_year, "%Y %b %e %H:%M:%S").ok()?;
+	let time = match time.and_utc().timestamp() > now.naive_local().and_utc().timestamp() + 86400 {
+		true => time.with_year(now.year() - 1)?,
+		false => time,
+	};
+	local_time(&time)
+}
+
+fn local_time(time: &NaiveDateTime) -> Option<SystemTime> {
+	Local
+		.from_local_datetime(time)
+		.earliest()
+		.map(SystemTime::from)
+}
+
+/// Parse a time given by the user, such as "2024-01-02 03:04:05",
+/// "2024-01-02T03:04:05Z", "2024-01-02" or "03:04" (today)
+pub fn parse_time(text: &str) -> Result<SystemTime, String> {
+	let text = text.trim();
+	if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+		return Ok(time.into());
+	}
+	for format in [
+		"%Y-%m-%d %H:%M:%S",
+		"%Y-%m-%dT%H:%M:%S",
+		"%Y-%m-%d %H:%M",
+		"%Y-%m-%dT%H:%M",
+	]
+	.iter()
+	{
+		if let Ok(time) = NaiveDateTime::parse_from_str(text, format) {
+			return local_time(&time).ok_or(format!("invalid local time: {}", text));
+		}
+	}
+	if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+		if let Some(time) = date.and_hms_opt(0, 0, 0) {
+			return local_time(&time).ok_or(format!("invalid local time: {}", text));
+		}
+	}
+	for format in ["%H:%M:%S", "%H:%M"].iter() {
+		if let Ok(time) = chrono::NaiveTime::parse_from_str(text, format) {
+			let today = Local::now().date_naive().and_time(time);
+			return local_time(&today).ok_or(format!("invalid local time: {}", text));
+		}
+	}
+	Err(format!("invalid time: {}", text))
+}
+
+/// Parse a duration such as "90s", "5m", "2h" or "1d" (a number alone is seconds)
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+	let text = text.trim();
+	let digits_end = text
+		.find(|c: char| !c.is_ascii_digit())
+		.unwrap_or(text.len());
+	let number = text[..digits_end]
+		.parse::<u64>()
+		.map_err(|_| format!("invalid duration: {}", text))?;
+	let unit = match &text[digits_end..] {
+		"" | "s" => 1,
+		"m" => 60,
+		"h" => 60 * 60,
+		"d" => 24 * 60 * 60,
+		_ => return Err(format!("invalid duration: {}", text)),
+	};
+	match number.checked_mul(unit) {
+		Some(seconds) => Ok(Duration::from_secs(seconds)),
+		None => Err(format!("duration too long: {}", text)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn parse_duration_rejects_overflow() {
+		use std::time::Duration;
+		let parse = super::parse_duration;
+		assert_eq!(parse("90m"), Ok(Duration::from_secs(90 * 60)));
+		assert!(parse("99999999999999999d").is_err());
+		assert!(parse("99999999999999999999").is_err());
+	}
+}
@@ -1,7 +1,39 @@
 use rand::distributions::{Distribution, Uniform};
 use rand::rngs::ThreadRng;
+use std::collections::VecDeque;
+use std::time::Duration;
 use tui::widgets::ListState;
 
+/// Format a number of bytes in human readable units (e.g. 1.5 MB)
+pub fn format_size(bytes: u64) -> String {
+	let units = ["B", "KB", "MB", "GB", "TB"];
+	let mut size = bytes as f64;
+	let mut unit = 0;
+	while size >= 1024.0 && unit < units.len() - 1 {
+		size /= 1024.0;
+		unit += 1;
+	}
+	if unit == 0 {
+		format!("{} {}", bytes, units[0])
+	} else {
+		format!("{:.1} {}", size, units[unit])
+	}
+}
+
+/// Format a duration compactly using its largest unit (e.g. 3s, 5m, 2h, 4d)
+pub fn format_age(age: Duration) -> String {
+	let seconds = age.as_secs();
+	if seconds < 60 {
+		format!("{}s", seconds)
+	} else if seconds < 60 * 60 {
+		format!("{}m", seconds / 60)
+	} else if seconds < 24 * 60 * 60 {
+		format!("{}h", seconds / (60 * 60))
+	} else {
+		format!("{}d", seconds / (24 * 60 * 60))
+	}
+}
+
 #[derive(Clone)]
 pub struct RandomSignal {
 	distribution: Uniform<u64>,
@@ -126,3 +158,70 @@ impl<T> StatefulList<T> {
 		self.state.select(None);
 	}
 }
+
+/// A StatefulList holding at most `capacity` items, dropping the oldest
+/// to make room for new ones
+///
+/// Items are indexed and selected as in a StatefulList, oldest first.
+/// Pushing is O(1), and the selection stays on the same item as older
+/// items are dropped, or moves to the new item if the newest was selected.
+pub struct RingList<T> {
+	pub state: ListState,
+	pub items: VecDeque<T>,
+	capacity: usize,
+}
+
+impl<T> RingList<T> {
+	pub fn new(capacity: usize) -> RingList<T> {
+		RingList {
+			state: ListState::default(),
+			items: VecDeque::new(),
+			capacity: capacity.max(1),
+		}
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+
+	/// Change the capacity, returning the oldest items if any were dropped
+	pub fn set_capacity(&mut self, capacity: usize) -> Vec<T> {
+		self.capacity = capacity.max(1);
+		let excess = self.items.len().saturating_sub(self.capacity);
+		let dropped = self.items.drain(..excess).collect();
+		if let Some(selected) = self.state.selected() {
+			let selected = match self.items.is_empty() {
+				true => None,
+				false => Some(selected.saturating_sub(excess)),
+			};
+			self.state.select(selected);
+		}
+		dropped
+	}
+
+	/// Add an item after the newest, returning the oldest if it was dropped
+	pub fn push(&mut self, item: T) -> Option<T> {
+		let following = match self.state.selected() {
+			Some(selected) => selected + 1 >= self.items.len(),
+			None => true,
+		};
+		let dropped = match self.items.len() >= self.capacity {
+			true => self.items.pop_front(),
+			false => None,
+		};
+		self.items.push_back(item);
+
+		let selected = match (following, self.state.selected()) {
+			(false, Some(selected)) if dropped.is_some() => selected.saturating_sub(1),
+			(false, Some(selected)) => selected,
+			_ => self.items.len() - 1,
+		};
+		self.state.select(Some(selected));
+		dropped
+	}
+
+	pub fn clear(&mut self) {
+		self.items.clear();
+		self.state.select(None);
+	}
+}
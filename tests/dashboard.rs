@@ -0,0 +1,1127 @@
+//! Snapshot tests of the dashboard, rendered with tui's TestBackend
+//!
+//! Each test builds an App without reading the command line, feeds it
+//! scripted lines and key presses, and compares the rendered text with a
+//! snapshot in tests/snapshots. A missing snapshot fails the test. To add
+//! one, or after an intended change to the layout, review the differences
+//! and update the snapshots with:
+//!
+//!   UPDATE_SNAPSHOTS=1 cargo test
+#[path = "../src/custom/mod.rs"]
+pub mod custom;
+#[path = "../src/mod.rs"]
+pub mod shared;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::{backend::TestBackend, Terminal};
+
+use custom::app::{App, DashViewMain, LogLine, Severity};
+use custom::config::Config;
+use custom::keymap::{Action, Keymap};
+use custom::opt::{Opt, StructOpt};
+use custom::replay::Replay;
+use custom::session::Session;
+use custom::theme::Theme;
+use custom::ui::draw_dashboard;
+use shared::syslog::SyslogMessage;
+
+/// An App for the given command line arguments, with the default configuration
+///
+/// The logfiles named needn't exist, and aren't followed for new lines.
+fn app_with_args(args: &[&str]) -> App {
+	let opt = Opt::from_iter(std::iter::once("logtail").chain(args.iter().copied()));
+	App::with_config(opt, Config::default()).expect("unable to create App")
+}
+
+/// Append lines to a monitor as if they had arrived from its logfile
+///
+/// Activity is cleared so that the activity strip doesn't depend on timing.
+fn append_lines(app: &mut App, logfile: &str, lines: &[&str]) {
+	let monitor = app.monitors.get_mut(logfile).expect("no monitor for logfile");
+	for line in lines {
+		monitor.append_to_content(line);
+	}
+	monitor.activity.clear();
+}
+
+/// Press keys, named as in the keymap and separated by spaces
+fn press_keys(app: &mut App, keys: &str) {
+	for key in keys.split_whitespace() {
+		if let Some(action) = app.handle_key(key) {
+			app.do_action(action);
+		}
+	}
+}
+
+/// Type a command after ':', without pressing Enter
+fn type_command(app: &mut App, command: &str) {
+	press_keys(app, ":");
+	for c in command.chars() {
+		press_keys(app, &c.to_string().replace(' ', "Space"));
+	}
+}
+
+/// Draw the dashboard and return the text of the screen, one line per row
+fn render(app: &mut App, width: u16, height: u16) -> String {
+	let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+	terminal
+		.draw(|f| draw_dashboard(f, &mut app.dash_state, &mut app.monitors))
+		.unwrap();
+
+	let buffer = terminal.backend().buffer();
+	let mut text = String::new();
+	for y in 0..buffer.area.height {
+		let row: String = (0..buffer.area.width)
+			.map(|x| buffer.get(x, y).symbol.as_str())
+			.collect();
+		text.push_str(row.trim_end());
+		text.push('\n');
+	}
+	mask_buffer_sizes(&text)
+}
+
+/// Hide the buffer sizes, which depend on the platform, keeping the layout
+fn mask_buffer_sizes(text: &str) -> String {
+	let sizes = regex::Regex::new(r"buffers( +[0-9.]+ [KMGT]?B)").unwrap();
+	sizes
+		.replace_all(text, |captures: &regex::Captures| {
+			format!("buffers {}", "#".repeat(captures[1].len() - 1))
+		})
+		.into_owned()
+}
+
+/// Compare the rendering with a snapshot, written instead when UPDATE_SNAPSHOTS is set
+fn assert_snapshot(name: &str, rendered: &str) {
+	let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "snapshots", name]
+		.iter()
+		.collect::<PathBuf>()
+		.with_extension("txt");
+
+	if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(&path, rendered).unwrap();
+		return;
+	}
+
+	let expected = match std::fs::read_to_string(&path) {
+		Ok(expected) => expected,
+		Err(_) => panic!(
+			"missing snapshot {}, create it with UPDATE_SNAPSHOTS=1\n--- rendered\n{}",
+			path.display(),
+			rendered
+		),
+	};
+	if rendered != expected {
+		panic!(
+			"rendering differs from snapshot {}\n--- expected\n{}--- rendered\n{}",
+			path.display(),
+			expected,
+			rendered
+		);
+	}
+}
+
+/// A directory for a test's files, removed when dropped even if the test fails
+struct TempDir(PathBuf);
+
+impl TempDir {
+	fn new(name: &str) -> TempDir {
+		let dir = std::env::temp_dir().join(format!("logtail-{}-{}", name, std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir); // Left by an earlier run
+		std::fs::create_dir_all(&dir).unwrap();
+		TempDir(dir)
+	}
+}
+
+impl std::ops::Deref for TempDir {
+	type Target = Path;
+
+	fn deref(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_dir_all(&self.0);
+	}
+}
+
+fn app_with_two_logfiles() -> App {
+	let mut app = app_with_args(&["alpha.log", "beta.log"]);
+	append_lines(
+		&mut app,
+		"alpha.log",
+		&[
+			"INFO starting alpha",
+			"WARN disk 91% full",
+			"ERROR unable to write block 42",
+		],
+	);
+	append_lines(&mut app, "beta.log", &["beta line one", "beta line two"]);
+	app
+}
+
+#[test]
+fn horizontal_layout() {
+	let mut app = app_with_two_logfiles();
+	assert_snapshot("horizontal_layout", &render(&mut app, 60, 16));
+}
+
+#[test]
+fn vertical_layout() {
+	let mut app = app_with_two_logfiles();
+	press_keys(&mut app, "v");
+	assert_snapshot("vertical_layout", &render(&mut app, 60, 16));
+}
+
+#[test]
+fn scroll_and_change_focus() {
+	let mut app = app_with_two_logfiles();
+	let lines: Vec<String> = (1..=30).map(|n| format!("beta line {}", n)).collect();
+	let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+	append_lines(&mut app, "beta.log", &lines);
+
+	press_keys(&mut app, "Tab g j j");
+	assert_eq!(app.logfile_with_focus, "beta.log");
+	let monitor = app.get_monitor_with_focus().unwrap();
+	assert_eq!(monitor.content.state.selected(), Some(2));
+	assert_snapshot("scroll_and_change_focus", &render(&mut app, 60, 16));
+}
+
+#[test]
+fn template_filter() {
+	let mut app = app_with_args(&["alpha.log"]);
+	append_lines(
+		&mut app,
+		"alpha.log",
+		&[
+			"user 1001 logged in",
+			"disk check passed",
+			"user 1002 logged in",
+			"user 1003 logged in",
+		],
+	);
+
+	// Select the first template, then show only its lines
+	press_keys(&mut app, "t g Enter");
+	assert_snapshot("template_filter", &render(&mut app, 60, 10));
+}
+
+#[test]
+fn small_terminal() {
+	let mut app = app_with_two_logfiles();
+	assert_snapshot("small_terminal", &render(&mut app, 20, 5));
+	press_keys(&mut app, "v");
+	render(&mut app, 3, 2);
+}
+
+#[test]
+fn configured_keys_and_sequences() {
+	let dir = TempDir::new("keys");
+	let config_path = dir.join("config.toml");
+	std::fs::write(
+		&config_path,
+		"[keys]\n\"Z Z\" = \"quit\"\n\"n\" = \"scroll-down\"\n\"j\" = \"none\"\n",
+	)
+	.unwrap();
+	let config = Config::load(Some(&config_path.to_string_lossy())).unwrap();
+	let opt = Opt::from_iter(&["logtail", "alpha.log"]);
+	let mut app = App::with_config(opt, config).unwrap();
+
+	// A sequence completes only on its last key
+	assert_eq!(app.handle_key("Z"), None);
+	assert_eq!(app.handle_key("Z"), Some(Action::Quit));
+
+	// A key that doesn't continue a sequence starts a new one
+	assert_eq!(app.handle_key("Z"), None);
+	assert_eq!(app.handle_key("x"), Some(Action::ClosePane));
+
+	// Bindings added and removed, with the defaults kept otherwise
+	assert_eq!(app.handle_key("n"), Some(Action::ScrollDown));
+	assert_eq!(app.handle_key("j"), None);
+	assert_eq!(app.handle_key("k"), Some(Action::ScrollUp));
+	assert!(app
+		.keymap
+		.describe()
+		.iter()
+		.any(|line| line.starts_with("quit") && line.ends_with("q, Q, Z Z")));
+
+	// Unknown actions and keys are errors
+	let mut config = Config::default();
+	config.keys.insert(String::from("j"), String::from("jump"));
+	assert!(Keymap::from_config(&config).is_err());
+	let mut config = Config::default();
+	config.keys.insert(String::from("Ctrl-j"), String::from("quit"));
+	assert!(Keymap::from_config(&config).is_err());
+}
+
+#[test]
+fn themes_from_config_and_no_colour() {
+	let dir = TempDir::new("themes");
+	let config_path = dir.join("config.toml");
+	std::fs::write(
+		&config_path,
+		"theme = \"mine\"\n[themes.mine]\nbase = \"dark\"\nerror = \"magenta bold\"\n",
+	)
+	.unwrap();
+	let config = Config::load(Some(&config_path.to_string_lossy())).unwrap();
+	let error = Style::default()
+		.fg(Color::Magenta)
+		.add_modifier(Modifier::BOLD);
+	let theme = Theme::from_config(config.theme.as_ref().unwrap(), &config.themes).unwrap();
+	assert_eq!(theme.error, error);
+	assert_eq!(theme.warning, Theme::named("dark").unwrap().warning);
+	assert!(Theme::from_config("nonesuch", &config.themes).is_err());
+
+	// Severity is found in the first words, even when coloured
+	assert_eq!(theme.severity(Severity::of_line("ERROR disk full")), error);
+	assert_eq!(
+		Severity::of_line("\x1b[31mERROR\x1b[0m disk full"),
+		Severity::Error
+	);
+	assert_eq!(
+		Severity::of_line("12:00:01 \x1b[1;33mwarn\x1b[0m slow"),
+		Severity::Warning
+	);
+
+	// NO_COLOR keeps modifiers but drops colours, and embedded styling
+	std::env::set_var("NO_COLOR", "1");
+	let opt = Opt::from_iter(&["logtail", "alpha.log"]);
+	let app = App::with_config(opt, config);
+	std::env::remove_var("NO_COLOR");
+	let app = app.unwrap();
+	assert_eq!(
+		app.dash_state.theme.error,
+		Style::default().add_modifier(Modifier::BOLD)
+	);
+	assert!(app.dash_state.strip_ansi);
+}
+
+#[test]
+fn time_window_bounds() {
+	let mut app = app_with_args(&[
+		"--since",
+		"2024-01-02 03:04:05",
+		"--until",
+		"2024-01-02 03:05:00",
+		"alpha.log",
+	]);
+	append_lines(
+		&mut app,
+		"alpha.log",
+		&[
+			"2024-01-02 03:04:04 just before",
+			"2024-01-02 03:04:05 at the start",
+			"2024-01-02 03:05:00 at the end",
+			"2024-01-02 03:05:01 just after",
+			"no timestamp, so its arrival time now",
+		],
+	);
+	assert_eq!(app.monitors["alpha.log"].shown_lines(), vec![1, 2]);
+
+	// Toggled off, every line is shown
+	press_keys(&mut app, "w");
+	assert_eq!(app.monitors["alpha.log"].shown_lines().len(), 5);
+
+	// A relative window runs up to now, and a time in the future is within it
+	let mut app = app_with_args(&["--last", "5m", "alpha.log"]);
+	let format = |age: i64| {
+		(chrono::Local::now() - chrono::Duration::seconds(age))
+			.format("%Y-%m-%d %H:%M:%S")
+			.to_string()
+	};
+	let lines = [
+		format!("{} ten minutes ago", format(600)),
+		format!("{} four minutes ago", format(240)),
+		format!("{} in a minute", format(-60)),
+		String::from("no timestamp"),
+	];
+	let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+	append_lines(&mut app, "alpha.log", &lines);
+	assert_eq!(app.monitors["alpha.log"].shown_lines(), vec![1, 2, 3]);
+	assert!(app.next_wakeup().is_some());
+}
+
+#[test]
+fn arrival_times_in_gutter() {
+	let dir = TempDir::new("gutter");
+	let path = dir.join("alpha.log");
+	std::fs::write(&path, "loaded line\n").unwrap();
+	let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+	let logfile = path.to_str().unwrap();
+	let mut app = app_with_args(&[logfile]);
+
+	// Loaded lines arrived when the logfile was modified
+	let monitor = app.monitors.get_mut(logfile).unwrap();
+	assert_eq!(monitor.content.items[0].arrived, Some(modified));
+	let earlier = SystemTime::now() - Duration::from_secs(90);
+	monitor.append_line("earlier line", Some(earlier));
+	monitor.append_line("unknown line", None);
+	monitor.append_to_content("new line");
+
+	let row = |rendered: &str, text: &str| -> String {
+		let row = rendered.lines().find(|row| row.contains(text)).unwrap();
+		row.trim_start_matches('│').to_string()
+	};
+	let rendered = render(&mut app, 80, 8);
+	assert!(row(&rendered, "earlier line").starts_with("earlier line"));
+
+	press_keys(&mut app, "T");
+	let rendered = render(&mut app, 80, 8);
+	let time = chrono::DateTime::<chrono::Local>::from(earlier).format("%H:%M:%S");
+	assert!(row(&rendered, "earlier line").starts_with(&format!("{} earlier line", time)));
+	assert!(row(&rendered, "unknown line").starts_with("       ? unknown line"));
+
+	press_keys(&mut app, "T");
+	let rendered = render(&mut app, 80, 8);
+	assert!(row(&rendered, "earlier line").starts_with("  1m ago earlier line"));
+	assert!(row(&rendered, "new line").starts_with("  0s ago new line"));
+
+	press_keys(&mut app, "T");
+	let rendered = render(&mut app, 80, 8);
+	assert!(row(&rendered, "new line").starts_with("new line"));
+}
+
+#[test]
+fn highlight_rules_from_config() {
+	let dir = TempDir::new("highlight");
+	let config_path = dir.join("config.toml");
+	std::fs::write(
+		&config_path,
+		"[[highlight]]\npattern = \"timeout\"\nstyle = \"bold red\"\n\n\
+		 [[highlight]]\npattern = \"tenant-[0-9]+\"\nstyle = \"cyan\"\nsource = \"beta.log\"\n",
+	)
+	.unwrap();
+	let config = Config::load(Some(&config_path.to_string_lossy())).unwrap();
+	let opt = Opt::from_iter(&["logtail", "alpha.log", "beta.log"]);
+	let mut app = App::with_config(opt, config).unwrap();
+
+	let base = Style::default().fg(Color::Yellow);
+	let apply = |app: &App, source: &str| -> Vec<(String, Style)> {
+		let line = Spans::from(Span::styled("tenant-7 timeout", base));
+		let spans = app.dash_state.highlighter.apply(source, line);
+		spans
+			.0
+			.into_iter()
+			.map(|span| (span.content.into_owned(), span.style))
+			.collect()
+	};
+
+	// Only the matches are styled, over the line's own style
+	let timeout = base.fg(Color::Red).add_modifier(Modifier::BOLD);
+	assert_eq!(
+		apply(&app, "alpha.log"),
+		vec![
+			(String::from("tenant-7 "), base),
+			(String::from("timeout"), timeout),
+		]
+	);
+
+	// Rules for a source apply only to that source
+	assert_eq!(
+		apply(&app, "beta.log"),
+		vec![
+			(String::from("tenant-7"), base.fg(Color::Cyan)),
+			(String::from(" "), base),
+			(String::from("timeout"), timeout),
+		]
+	);
+
+	// And all can be turned off
+	press_keys(&mut app, "i");
+	assert_eq!(
+		apply(&app, "beta.log"),
+		vec![(String::from("tenant-7 timeout"), base)]
+	);
+}
+
+#[test]
+fn debug_window() {
+	let mut app = app_with_args(&["--debug-window", "alpha.log"]);
+	app.read_logfile_lines(&"unknown.log".to_string());
+	let events = &app.dash_state.debug_window_list.items;
+	assert!(events.last().unwrap().ends_with("dropped line from unknown file unknown.log"));
+
+	press_keys(&mut app, "Tab");
+	assert!(app.dash_state.debug_window_has_focus);
+	press_keys(&mut app, "Tab");
+	assert_eq!(app.logfile_with_focus, "alpha.log");
+
+	let rendered = render(&mut app, 60, 16);
+	assert!(rendered.contains("dropped line from unknown file"));
+}
+
+#[test]
+fn debug_window_notes_rotation() {
+	let dir = TempDir::new("rotation");
+	let path = dir.join("rotating.log");
+	let logfile = path.to_str().unwrap().to_string();
+	std::fs::write(&path, "one\ntwo\n").unwrap();
+
+	let mut app = app_with_args(&["--debug-window", &logfile]);
+	app.on_tick();
+	std::fs::write(&path, "").unwrap();
+	app.on_tick();
+	std::fs::remove_file(&path).unwrap();
+	app.on_tick();
+
+	let events = &app.dash_state.debug_window_list.items;
+	assert!(events.iter().any(|e| e.ends_with(&format!("loaded 2 lines from {}", logfile))));
+	assert!(events.iter().any(|e| e.ends_with(&format!("{} truncated", logfile))));
+	assert!(events.last().unwrap().ends_with(&format!("{} removed", logfile)));
+}
+
+#[test]
+fn session_restores_dashboard() {
+	let mut app = app_with_two_logfiles();
+	let lines: Vec<String> = (1..=30).map(|n| format!("user {} logged in", n)).collect();
+	let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+	append_lines(&mut app, "beta.log", &lines);
+	press_keys(&mut app, "v Tab t G Enter k k T");
+
+	let saved = toml::to_string(&Session::from_app(&app)).unwrap();
+	let session: Session = toml::from_str(&saved).unwrap();
+	let pane = &session.panes["beta.log"];
+	assert_eq!(pane.template_filter.as_deref(), Some("user <*> logged in"));
+	assert_eq!(pane.lines_from_end, Some(2));
+	let mut restored = app_with_two_logfiles();
+	append_lines(&mut restored, "beta.log", &lines);
+	session.apply(&mut restored);
+
+	assert_eq!(restored.logfile_with_focus, "beta.log");
+	assert_eq!(render(&mut restored, 60, 16), render(&mut app, 60, 16));
+}
+
+#[test]
+fn session_restores_paused_pane() {
+	let lines: Vec<String> = (1..=30).map(|n| format!("line {}", n)).collect();
+	let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+	let mut app = app_with_args(&["alpha.log", "beta.log"]);
+	append_lines(&mut app, "alpha.log", &lines[..20]);
+	press_keys(&mut app, "k k k");
+	assert!(!app.monitors["alpha.log"].is_following());
+
+	let saved = toml::to_string(&Session::from_app(&app)).unwrap();
+	let session: Session = toml::from_str(&saved).unwrap();
+	assert!(session.panes["alpha.log"].paused);
+	assert!(!session.panes.contains_key("beta.log"));
+
+	// Restored paused, so new lines aren't followed
+	let mut restored = app_with_args(&["alpha.log", "beta.log"]);
+	append_lines(&mut restored, "alpha.log", &lines[..20]);
+	session.apply(&mut restored);
+	append_lines(&mut restored, "alpha.log", &lines[20..]);
+	let monitor = &restored.monitors["alpha.log"];
+	assert!(!monitor.is_following());
+	assert_eq!(monitor.content.state.selected(), Some(16));
+
+	// Even if there are now too few lines to scroll back as far
+	let mut restored = app_with_args(&["alpha.log", "beta.log"]);
+	append_lines(&mut restored, "alpha.log", &lines[..2]);
+	session.apply(&mut restored);
+	assert!(!restored.monitors["alpha.log"].is_following());
+}
+
+#[test]
+fn non_utf8_and_binary_logfiles() {
+	let dir = TempDir::new("encoding");
+	let logfile = |name: &str, bytes: &[u8]| {
+		let path = dir.join(name);
+		std::fs::write(&path, bytes).unwrap();
+		path.to_str().unwrap().to_string()
+	};
+	let invalid = logfile("invalid.log", b"caf\xe9 ok\nnext line\n");
+	let latin1 = logfile("latin1.log", b"caf\xe9 ok\n");
+	let utf16 = logfile("utf16.log", b"\xfe\xff\x00h\x00i\x00\n");
+	let binary = logfile("binary.log", b"\x7fELF\x02\x01\x01\x00\x00\x00\n");
+
+	let latin1_encoding = format!("{}=latin-1", latin1);
+	let app = app_with_args(&[
+		"--encoding",
+		"utf-16",
+		"--encoding",
+		"utf-8",
+		"--encoding",
+		&latin1_encoding,
+		&invalid,
+		&latin1,
+		&utf16,
+		&binary,
+	]);
+	let utf16_app = app_with_args(&["--encoding", "utf-16", &utf16]);
+
+	let texts = |app: &App, logfile: &str| -> Vec<String> {
+		let monitor = &app.monitors[logfile];
+		monitor.content.items.iter().map(|line| line.text.to_string()).collect()
+	};
+	assert_eq!(texts(&app, &invalid), ["caf\u{fffd} ok", "next line"]);
+	assert_eq!(texts(&app, &latin1), ["café ok"]);
+	assert_eq!(texts(&utf16_app, &utf16), ["hi"]);
+	let binary_texts = texts(&app, &binary);
+	assert_eq!(binary_texts[0], "binary content, 11 bytes (first 11 shown)");
+	assert!(binary_texts[1].starts_with("00000000  7f 45 4c 46 02 01 01 00"));
+	assert!(binary_texts[1].ends_with("|.ELF.......|"));
+}
+
+#[test]
+fn followed_lines_decoded_with_logfile_encoding() {
+	let dir = TempDir::new("follow");
+	let logfile = |name: &str, bytes: &[u8]| {
+		let path = dir.join(name);
+		std::fs::write(&path, bytes).unwrap();
+		path.to_str().unwrap().to_string()
+	};
+	let append = |path: &str, bytes: &[u8]| {
+		let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+		file.write_all(bytes).unwrap();
+	};
+	let latin1 = logfile("latin1.log", b"caf\xe9 loaded\n");
+	let utf16 = logfile("utf16.log", b"\xff\xfeo\x00k\x00\n\x00");
+	let latin1_encoding = format!("{}=latin-1", latin1);
+	let utf16_encoding = format!("{}=utf-16", utf16);
+	let mut app = app_with_args(&[
+		"--encoding",
+		&latin1_encoding,
+		"--encoding",
+		&utf16_encoding,
+		&latin1,
+		&utf16,
+	]);
+	let texts = |app: &App, logfile: &str| -> Vec<String> {
+		let monitor = &app.monitors[logfile];
+		monitor.content.items.iter().map(|line| line.text.to_string()).collect()
+	};
+
+	// Only complete lines are read, decoded as the lines loaded were
+	append(&latin1, b"na\xefve\nr\xe9");
+	let utf16_bytes: Vec<u8> = "h\u{e9}\n\u{2603}"
+		.encode_utf16()
+		.flat_map(u16::to_le_bytes)
+		.collect();
+	append(&utf16, &utf16_bytes);
+	app.read_logfile_lines(&latin1);
+	app.read_logfile_lines(&utf16);
+	assert_eq!(texts(&app, &latin1), ["café loaded", "naïve"]);
+	assert_eq!(texts(&app, &utf16), ["ok", "hé"]);
+	append(&latin1, b"sum\xe9\r\n");
+	app.read_logfile_lines(&latin1);
+	assert_eq!(texts(&app, &latin1), ["café loaded", "naïve", "résumé"]);
+
+	// A truncated logfile is read again from its start
+	std::fs::write(&latin1, b"new\n").unwrap();
+	app.read_logfile_lines(&latin1);
+	assert_eq!(texts(&app, &latin1).last().unwrap(), "new");
+
+	// And a rotated one once the rest of the old file has been read
+	append(&latin1, b"last\n");
+	std::fs::rename(&latin1, format!("{}.1", latin1)).unwrap();
+	std::fs::write(&latin1, b"first\n").unwrap();
+	app.read_logfile_lines(&latin1);
+	app.read_logfile_lines(&latin1);
+	let lines = texts(&app, &latin1);
+	assert_eq!(lines[lines.len() - 2..], ["last", "first"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn lines_found_by_any_path_to_logfile() {
+	let dir = TempDir::new("paths");
+	std::fs::create_dir(dir.join("sub")).unwrap();
+	let real = dir.join("real.log");
+	std::fs::write(&real, "").unwrap();
+	std::os::unix::fs::symlink(&real, dir.join("link.log")).unwrap();
+	let path = |p: PathBuf| p.to_str().unwrap().to_string();
+	let real = path(real);
+	let link = path(dir.join("link.log"));
+
+	let mut app = app_with_args(&["--debug-window", &real]);
+	for source in [path(dir.join("sub").join("..").join("real.log")), link.clone()].iter() {
+		let mut file = std::fs::OpenOptions::new().append(true).open(&real).unwrap();
+		writeln!(file, "{}", source).unwrap();
+		app.read_logfile_lines(source);
+	}
+	app.read_logfile_lines(&path(dir.join("other.log")));
+	let both = app_with_args(&["--debug-window", &real, &link]);
+
+	assert_eq!(app.monitors[&real].content.items.len(), 2);
+	let events = &app.dash_state.debug_window_list.items;
+	assert!(events.last().unwrap().contains("dropped line from unknown file"));
+	let events = &both.dash_state.debug_window_list.items;
+	assert!(events.iter().any(|e| e.ends_with(&format!("{} is the same file as {}", link, real))));
+}
+
+#[test]
+fn large_logfile_loads_tail_then_scans_history() {
+	let dir = TempDir::new("tail");
+	let path = dir.join("large.log");
+	let text: String = (1..=20000).map(|n| format!("line {}\n", n)).collect();
+	std::fs::write(&path, text.trim_end()).unwrap();
+	let logfile = path.to_str().unwrap().to_string();
+
+	// Enough lines to need several blocks read back from the end
+	let mut app = app_with_args(&["--full-scan", "--lines-max", "15000", &logfile]);
+	let monitor = &app.monitors[&logfile];
+	assert_eq!(monitor.content.items.len(), 15000);
+	assert_eq!(&*monitor.content.items[0].text, "line 5001");
+	assert_eq!(&*monitor.content.items[14999].text, "line 20000");
+	assert!(monitor.scan_percent_done().is_some());
+
+	while app.monitors[&logfile].scan.is_some() {
+		app.on_tick();
+	}
+
+	assert_eq!(app.monitors[&logfile].lines_seen, 20000);
+	assert_eq!(app.monitors[&logfile].content.items.len(), 15000);
+	let events = &app.dash_state.debug_window_list.items;
+	assert!(events.last().unwrap().ends_with(&format!("scanned 5000 earlier lines of {}", logfile)));
+}
+
+#[test]
+fn utf16_and_rotated_logfiles_load_only_tails() {
+	use std::io::Write;
+
+	let dir = TempDir::new("history");
+	let lines =
+		|prefix: &str| -> String { (1..=20000).map(|n| format!("{} {}\n", prefix, n)).collect() };
+
+	// Big-endian UTF-16, with its byte order mark only at the start
+	let path = dir.join("utf16.log");
+	let mut bytes = vec![0xfe, 0xff];
+	bytes.extend(
+		lines("wide")
+			.encode_utf16()
+			.flat_map(|unit| unit.to_be_bytes()),
+	);
+	std::fs::write(&path, bytes).unwrap();
+	let utf16 = path.to_str().unwrap().to_string();
+
+	let mut app = app_with_args(&[
+		"--encoding",
+		"utf-16",
+		"--full-scan",
+		"--lines-max",
+		"15000",
+		&utf16,
+	]);
+	let monitor = &app.monitors[&utf16];
+	assert_eq!(monitor.content.items.len(), 15000);
+	assert_eq!(&*monitor.content.items[0].text, "wide 5001");
+	assert_eq!(&*monitor.content.items[14999].text, "wide 20000");
+	while app.monitors[&utf16].scan.is_some() {
+		app.on_tick();
+	}
+	assert_eq!(app.monitors[&utf16].lines_seen, 20000);
+
+	// Each rotated logfile, compressed or not, gives only the lines still wanted
+	let path = dir.join("app.log");
+	std::fs::write(&path, "current\n").unwrap();
+	std::fs::write(dir.join("app.log.1"), lines("one")).unwrap();
+	let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+	gz.write_all(lines("two").as_bytes()).unwrap();
+	std::fs::write(dir.join("app.log.2.gz"), gz.finish().unwrap()).unwrap();
+	let logfile = path.to_str().unwrap().to_string();
+
+	let app = app_with_args(&["--rotated", "--lines-max", "30000", &logfile]);
+	let monitor = &app.monitors[&logfile];
+	let texts: Vec<&str> = monitor
+		.content
+		.items
+		.iter()
+		.map(|line| &*line.text)
+		.collect();
+	assert_eq!(monitor.lines_seen, 30000); // Including three markers
+	assert!(texts[0].ends_with("app.log.2.gz <=="));
+	assert_eq!(texts[1], "two 10005");
+	assert_eq!(texts[9996], "two 20000");
+	assert!(texts[9997].ends_with("app.log.1 <=="));
+	assert_eq!(texts[9998], "one 1");
+	assert!(texts[29998].ends_with("app.log <=="));
+	assert_eq!(texts[29999], "current");
+}
+
+#[test]
+fn selection_kept_as_oldest_lines_dropped() {
+	let mut app = app_with_args(&["--lines-max", "5", "alpha.log"]);
+	let lines: Vec<String> = (1..=5).map(|n| format!("line {}", n)).collect();
+	let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+	append_lines(&mut app, "alpha.log", &lines);
+	press_keys(&mut app, "k k");
+	append_lines(&mut app, "alpha.log", &["line 6", "line 7"]);
+
+	let monitor = app.get_monitor_with_focus().unwrap();
+	let texts: Vec<&str> = monitor.content.items.iter().map(|line| &*line.text).collect();
+	assert_eq!(texts, ["line 3", "line 4", "line 5", "line 6", "line 7"]);
+	assert_eq!(monitor.content.state.selected(), Some(0));
+
+	// The newest line is followed once selected
+	press_keys(&mut app, "G");
+	append_lines(&mut app, "alpha.log", &["line 8"]);
+	let monitor = app.get_monitor_with_focus().unwrap();
+	assert_eq!(monitor.content.state.selected(), Some(4));
+	assert_eq!(&*monitor.content.items[4].text, "line 8");
+}
+
+#[test]
+fn redraw_only_when_changed() {
+	let mut app = app_with_two_logfiles();
+	let size = tui::layout::Rect::new(0, 0, 60, 16);
+	assert!(app.needs_redraw(size));
+	app.mark_drawn(size);
+	assert!(!app.needs_redraw(size));
+
+	assert!(app.needs_redraw(tui::layout::Rect::new(0, 0, 80, 16)));
+	append_lines(&mut app, "beta.log", &["beta line three"]);
+	std::thread::sleep(app.frame_interval());
+	assert!(app.needs_redraw(size));
+	app.mark_drawn(size);
+	press_keys(&mut app, "Tab");
+	std::thread::sleep(app.frame_interval());
+	assert!(app.needs_redraw(size));
+}
+
+#[test]
+fn redraws_capped_by_max_fps() {
+	let mut app = app_with_args(&["--max-fps", "10", "alpha.log"]);
+	let size = tui::layout::Rect::new(0, 0, 60, 16);
+	app.mark_drawn(size);
+	assert_eq!(app.next_wakeup(), None); // Nothing shown depends on the time
+
+	// Changes wait for the next frame, but a resize is drawn at once
+	append_lines(&mut app, "alpha.log", &["line one"]);
+	assert!(!app.needs_redraw(size));
+	assert!(app.needs_redraw(tui::layout::Rect::new(0, 0, 80, 16)));
+	let due = app.next_wakeup().expect("no wakeup for the next frame");
+	assert!(due <= std::time::Instant::now() + app.frame_interval());
+	std::thread::sleep(app.frame_interval());
+	assert!(app.needs_redraw(size));
+
+	// Ages in the gutter need updating even when nothing else changes
+	press_keys(&mut app, "T T");
+	app.mark_drawn(size);
+	assert!(app.next_wakeup().is_some());
+}
+
+#[test]
+fn only_lines_in_view_drawn() {
+	let mut app = app_with_args(&["--lines-max", "100000", "alpha.log"]);
+	let lines: Vec<String> = (1..=100000).map(|n| format!("line {}", n)).collect();
+	let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+	append_lines(&mut app, "alpha.log", &lines);
+
+	let rendered = render(&mut app, 40, 8);
+	assert!(rendered.contains("line 99996") && rendered.contains("line 100000"));
+
+	// Scrolling up past the top of the view moves it only as far as needed
+	press_keys(&mut app, "k k k k k k");
+	assert_snapshot("only_lines_in_view_drawn", &render(&mut app, 40, 8));
+	assert_eq!(app.get_monitor_with_focus().unwrap().view_offset, 99993);
+}
+
+#[test]
+fn content_bytes_follow_lines_kept() {
+	let mut app = app_with_args(&["--lines-max", "10", "alpha.log"]);
+	let lines: Vec<String> = (0..20).map(|n| format!("line {:02}", n)).collect();
+	let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+	let bytes = |app: &App| app.monitors["alpha.log"].content_bytes();
+
+	// A line not repeated takes no more than 64 bytes besides its text
+	assert!(std::mem::size_of::<LogLine>() <= 64);
+
+	append_lines(&mut app, "alpha.log", &lines[..10]);
+	let short_lines = bytes(&app);
+
+	// A long line in place of a short one counts the difference
+	let long_line = "x".repeat(1000);
+	append_lines(&mut app, "alpha.log", &[&long_line]);
+	append_lines(&mut app, "alpha.log", &lines[10..19]);
+	assert_eq!(bytes(&app), short_lines + 1000 - "line 00".len());
+
+	// And nothing once it's dropped
+	append_lines(&mut app, "alpha.log", &lines[19..]);
+	assert_eq!(bytes(&app), short_lines);
+}
+
+#[test]
+fn command_line_filters_and_sets() {
+	let mut app = app_with_two_logfiles();
+	type_command(&mut app, "filter ERROR|WARN");
+	press_keys(&mut app, "Enter");
+	assert_snapshot("command_line_filter", &render(&mut app, 60, 12));
+
+	// The outcome is shown until the next key press
+	press_keys(&mut app, "k");
+	assert!(render(&mut app, 120, 12).contains("filter: ERROR|WARN"));
+
+	type_command(&mut app, "set lines_max 1");
+	press_keys(&mut app, "Enter");
+	assert_eq!(app.monitors["alpha.log"].content.items.len(), 1);
+	assert_eq!(app.monitors["beta.log"].content.items.len(), 1);
+
+	type_command(&mut app, "layout sideways");
+	press_keys(&mut app, "Enter");
+	assert_eq!(
+		app.dash_state.message,
+		Some(Err(String::from("layout: horizontal or vertical needed")))
+	);
+}
+
+#[test]
+fn filter_ignores_escape_sequences() {
+	let mut app = app_with_args(&["alpha.log"]);
+	append_lines(
+		&mut app,
+		"alpha.log",
+		&[
+			"\x1b[31mERROR\x1b[0m disk full",
+			"\x1b[32mINFO\x1b[0m disk checked",
+			"ERROR: \x1b[1mdisk\x1b[0m gone",
+		],
+	);
+
+	type_command(&mut app, "filter ^ERROR:? disk");
+	press_keys(&mut app, "Enter");
+	assert_eq!(app.monitors["alpha.log"].shown_lines(), vec![0, 2]);
+	let rendered = render(&mut app, 60, 8);
+	assert!(rendered.contains("ERROR disk full") && !rendered.contains("INFO"));
+}
+
+#[test]
+fn command_line_history_and_completion() {
+	let mut app = app_with_two_logfiles();
+	type_command(&mut app, "la");
+	press_keys(&mut app, "Tab v Tab Enter");
+	assert_eq!(app.dash_state.main_view, DashViewMain::DashVertical);
+
+	type_command(&mut app, "highlight disk");
+	press_keys(&mut app, "Enter : Up Up");
+	assert_eq!(app.dash_state.command_line.input, "layout vertical");
+	press_keys(&mut app, "Down");
+	assert_eq!(app.dash_state.command_line.input, "highlight disk");
+	press_keys(&mut app, "Esc");
+	assert!(!app.dash_state.command_line.active);
+
+	type_command(&mut app, "set ");
+	press_keys(&mut app, "Tab");
+	assert_snapshot("command_line_completions", &render(&mut app, 60, 12));
+}
+
+#[test]
+fn command_line_opens_saves_and_closes() {
+	let dir = TempDir::new("command");
+	let path = dir.join("opened.log");
+	let logfile = path.to_str().unwrap().to_string();
+	std::fs::write(&path, "first\nERROR second\nthird\n").unwrap();
+
+	let mut app = app_with_two_logfiles();
+	type_command(&mut app, &format!("open {}", logfile));
+	press_keys(&mut app, "Enter");
+	assert_eq!(app.logfile_with_focus, logfile);
+	assert_eq!(app.logfile_names.last(), Some(&logfile));
+	assert_eq!(app.opened, vec![logfile.clone()]);
+
+	let saved = dir.join("saved.log");
+	type_command(&mut app, "filter ERROR");
+	press_keys(&mut app, "Enter");
+	type_command(&mut app, &format!("save {}", saved.to_str().unwrap()));
+	press_keys(&mut app, "Enter");
+	assert_eq!(std::fs::read_to_string(&saved).unwrap(), "ERROR second\n");
+
+	type_command(&mut app, "close");
+	press_keys(&mut app, "Enter");
+	assert!(!app.monitors.contains_key(&logfile));
+	assert_eq!(app.logfile_names, vec!["alpha.log", "beta.log"]);
+	assert_eq!(app.logfile_with_focus, "beta.log");
+}
+
+fn is_repeat_row(row: &str) -> bool {
+	row.starts_with("│  ↳ ") && row.trim_end_matches([' ', '│']).ends_with(" disk full")
+}
+
+#[test]
+fn expanded_repeats_shown_with_those_not_kept() {
+	let mut app = app_with_args(&["alpha.log"]);
+	let lines = vec!["disk full"; 103];
+	append_lines(&mut app, "alpha.log", &lines);
+	press_keys(&mut app, "Enter");
+
+	// Exact repeats show the line's text, after a note of those not kept
+	let rendered = render(&mut app, 40, 110);
+	let rows: Vec<&str> = rendered.lines().collect();
+	assert!(rows[1].starts_with("│disk full (×103, last"));
+	assert!(rows[2].starts_with("│  … 2 earlier repeats not kept"));
+	assert!(is_repeat_row(rows[3]));
+	assert!(is_repeat_row(rows[102]));
+
+	// And only as many as fit in the pane
+	let rendered = render(&mut app, 40, 8);
+	let rows: Vec<&str> = rendered.lines().collect();
+	assert!(rows[2].starts_with("│  … 99 earlier repeats not shown"));
+	assert!(is_repeat_row(rows[5]));
+}
+
+#[test]
+fn save_notes_repeats_not_kept() {
+	let dir = TempDir::new("save");
+	let saved = dir.join("saved.log");
+	let mut app = app_with_args(&["--collapse", "similar", "alpha.log"]);
+	let lines: Vec<String> = (0..103).map(|n| format!("retry {}", n)).collect();
+	let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+	append_lines(&mut app, "alpha.log", &lines);
+
+	type_command(&mut app, &format!("save {}", saved.to_str().unwrap()));
+	press_keys(&mut app, "Enter");
+	let expected: String = std::iter::once(String::from("retry 0"))
+		.chain(std::iter::once(String::from("(2 earlier repeats not kept)")))
+		.chain((3..103).map(|n| format!("retry {}", n)))
+		.map(|line| line + "\n")
+		.collect();
+	assert_eq!(std::fs::read_to_string(&saved).unwrap(), expected);
+}
+
+#[test]
+fn file_picker_opens_logfile() {
+	let dir = TempDir::new("picker");
+	std::fs::create_dir(dir.join("nested")).unwrap();
+	std::fs::write(dir.join("first.log"), "first line\n").unwrap();
+	std::fs::write(dir.join("second.log"), "second line\n").unwrap();
+	std::fs::write(dir.join("nested").join("third.log"), "third line\n").unwrap();
+	let first = dir.join("first.log").to_str().unwrap().to_string();
+
+	let mut app = app_with_args(&[&first]);
+	press_keys(&mut app, "o");
+	let rendered = render(&mut app, 60, 12);
+	assert!(rendered.contains("nested/") && rendered.contains("second.log"));
+
+	// Typing narrows the entries, Enter goes into a directory or opens a file
+	press_keys(&mut app, "n Enter Enter");
+	let third = dir.join("nested").join("third.log");
+	let third = third.canonicalize().unwrap().to_str().unwrap().to_string();
+	assert!(app.dash_state.file_picker.is_none());
+	assert_eq!(app.logfile_names, vec![first.clone(), third.clone()]);
+	assert_eq!(app.logfile_with_focus, third);
+	assert_eq!(app.opened, vec![third.clone()]);
+
+	// Backspace goes up a directory, and Esc closes the picker
+	press_keys(&mut app, "o Backspace");
+	assert!(render(&mut app, 60, 12).contains("first.log"));
+	press_keys(&mut app, "Esc");
+	assert!(app.dash_state.file_picker.is_none());
+}
+
+#[test]
+fn closing_panes_keeps_focus_order() {
+	let mut app = app_with_args(&["alpha.log", "beta.log", "gamma.log"]);
+	press_keys(&mut app, "Tab x");
+	assert_eq!(app.logfile_names, vec!["alpha.log", "gamma.log"]);
+	assert_eq!(app.logfile_with_focus, "gamma.log");
+	assert!(app.get_monitor_for_file_path(&String::from("beta.log")).is_none());
+
+	// The closed logfile is still followed, but its lines are dropped quietly
+	app.read_logfile_lines(&String::from("beta.log"));
+	let events = &app.dash_state.debug_window_list.items;
+	assert!(!events.iter().any(|e| e.contains("dropped line")));
+
+	// A closed syslog pane isn't opened again by the next message
+	let message = SyslogMessage::parse("<13>Oct 11 22:14:15 host app: hello");
+	app.append_syslog_message(&message);
+	assert_eq!(app.logfile_names, vec!["alpha.log", "gamma.log", "syslog"]);
+	app.close_logfile("syslog").unwrap();
+	app.append_syslog_message(&message);
+	assert_eq!(app.logfile_names, vec!["alpha.log", "gamma.log"]);
+
+	press_keys(&mut app, "x x");
+	assert!(app.monitors.is_empty() && app.logfile_with_focus.is_empty());
+	assert_snapshot("no_logfiles_open", &render(&mut app, 60, 6));
+	press_keys(&mut app, "x");
+	assert_eq!(
+		app.dash_state.message,
+		Some(Err(String::from("no logfile has focus")))
+	);
+}
+
+#[test]
+fn replay_speed_must_be_positive() {
+	let parse = |speed: &str| {
+		let speed = format!("--replay-speed={}", speed);
+		Opt::from_iter_safe(&["logtail", "--replay", &speed, "alpha.log"])
+	};
+	assert_eq!(parse("0.5").map(|opt| opt.replay_speed).ok(), Some(0.5));
+	for speed in &["-1", "0", "NaN", "inf", "fast"] {
+		assert!(parse(speed).is_err(), "--replay-speed {} accepted", speed);
+	}
+}
+
+#[test]
+fn replay_merges_files_in_their_own_order() {
+	let dir = TempDir::new("replay-merge");
+	let logfile = |name: &str, text: &str| {
+		let path = dir.join(name);
+		std::fs::write(&path, text).unwrap();
+		path.to_str().unwrap().to_string()
+	};
+	let alpha = logfile(
+		"alpha.log",
+		"2024-01-02 03:00:00 a1\n2024-01-02 03:00:20 a2\n2024-01-02 03:00:10 a3 late\n",
+	);
+	let beta = logfile(
+		"beta.log",
+		"2024-01-02 03:00:05 b1\n  continued\n2024-01-02 03:00:30 b2\n",
+	);
+	let opt = Opt::from_iter(&["logtail", "--replay", &alpha, &beta]);
+	let replay = Replay::load(&opt).unwrap();
+
+	// Without the timestamps
+	let texts: Vec<&str> = replay
+		.lines
+		.iter()
+		.map(|line| line.text.get(20..).unwrap_or(&line.text))
+		.collect();
+	assert_eq!(texts, ["a1", "b1", "  continued", "a2", "a3 late", "b2"]);
+}
+
+#[test]
+fn replay_paced_by_timestamps() {
+	let dir = TempDir::new("replay-pace");
+	let path = dir.join("alpha.log");
+	std::fs::write(
+		&path,
+		"2024-01-02 03:00:00 start\n2024-01-02 03:10:00 ten minutes on\n2024-02-02 03:00:00 a month on\n",
+	)
+	.unwrap();
+	let opt = Opt::from_iter(&[
+		"logtail",
+		"--replay",
+		"--replay-speed",
+		"65536",
+		path.to_str().unwrap(),
+	]);
+	let mut replay = Replay::load(&opt).unwrap();
+
+	// Lines are due once the clock reaches them, at the replay speed
+	assert_eq!(replay.release_due().len(), 1);
+	std::thread::sleep(Duration::from_millis(20));
+	assert_eq!(replay.tick().len(), 1);
+	assert!(!replay.is_finished());
+
+	// The clock stands still while paused
+	replay.paused = true;
+	let clock = replay.clock;
+	std::thread::sleep(Duration::from_millis(20));
+	assert!(replay.tick().is_empty());
+	assert_eq!(replay.clock, clock);
+
+	// Stepping releases the next line, moving the clock to it
+	assert_eq!(replay.step()[0].text, "2024-02-02 03:00:00 a month on");
+	assert!(replay.is_finished());
+
+	// Seeking back returns to the start so lines can be released again
+	replay.seek_back(Duration::from_secs(60 * 60 * 24 * 365));
+	assert_eq!(replay.position, 0);
+	assert_eq!(replay.seek_forward(Duration::from_secs(600)).len(), 2);
+}